@@ -0,0 +1,72 @@
+//! Exponential backoff between attempts
+//!
+//! When a connection to one of the cloud services fails we don't want to
+//! hammer it with immediate retries.  A [`Backoff`] describes how long to
+//! wait before each successive attempt.
+
+use std::time::Duration;
+
+/// An exponential backoff schedule
+///
+/// The delay starts at `initial` and doubles with each consecutive failure
+/// until it reaches `maximum`, where it stays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    initial: Duration,
+    maximum: Duration,
+}
+
+impl Backoff {
+    /// Create a backoff schedule
+    ///
+    /// ```
+    /// # use remsync_api_client::Backoff;
+    /// # use std::time::Duration;
+    /// let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(5));
+    /// assert_eq!(backoff.initial(), Duration::from_millis(100));
+    /// ```
+    pub fn new(initial: Duration, maximum: Duration) -> Self {
+        Self { initial, maximum }
+    }
+
+    /// The delay after the first failure
+    pub fn initial(&self) -> Duration {
+        self.initial
+    }
+
+    /// The longest delay this schedule will produce
+    pub fn maximum(&self) -> Duration {
+        self.maximum
+    }
+
+    /// The delay to wait after the given number of consecutive failures
+    ///
+    /// No failures means no delay.
+    ///
+    /// ```
+    /// # use remsync_api_client::Backoff;
+    /// # use std::time::Duration;
+    /// let backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+    /// assert_eq!(backoff.delay(0), Duration::from_secs(0));
+    /// assert_eq!(backoff.delay(1), Duration::from_secs(1));
+    /// assert_eq!(backoff.delay(3), Duration::from_secs(4));
+    /// assert_eq!(backoff.delay(10), Duration::from_secs(5));
+    /// ```
+    pub fn delay(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return Duration::from_secs(0);
+        }
+        // Beyond 2^16 times the initial delay we're certainly at the maximum
+        let factor = 1u32 << (failures - 1).min(16);
+        self.initial
+            .checked_mul(factor)
+            .map_or(self.maximum, |delay| delay.min(self.maximum))
+    }
+}
+
+impl Default for Backoff {
+    /// One second, doubling up to a minute
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
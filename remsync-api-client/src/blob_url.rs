@@ -0,0 +1,143 @@
+//! Signed blob URLs
+//!
+//! Blobs are not transferred through the storage API itself.  Instead the
+//! storage API mints a signed URL on the blob store, valid until a stated
+//! expiry time, and the blob is fetched from or put to that URL directly.
+//! A [`BlobUrl`] keeps the URL together with its expiry so that callers
+//! can tell when it is time to ask for a fresh one.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use remsync_api_types::*;
+
+use crate::{Error, Result};
+
+/// A signed URL for fetching or putting a blob
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlobUrl {
+    url: String,
+    expires: Option<SystemTime>,
+}
+
+impl BlobUrl {
+    /// Create a blob URL which expires at the given time, if known
+    ///
+    /// ```
+    /// # use remsync_api_client::BlobUrl;
+    /// let url = BlobUrl::new("https://blobs.example/some-blob", None);
+    /// ```
+    pub fn new<S: Into<String>>(url: S, expires: Option<SystemTime>) -> Self {
+        Self {
+            url: url.into(),
+            expires,
+        }
+    }
+
+    /// The blob URL from which a node's blob can be fetched
+    ///
+    /// Fails if the server didn't provide a URL.
+    pub fn for_download(doc: &DocsResponse) -> Result<Self> {
+        if doc.blob_url_get().is_empty() {
+            return Err(Error::protocol(
+                "GetDocsList",
+                format!("Blob URL missing for {}", doc.id()),
+            ));
+        }
+        Ok(Self::new(
+            doc.blob_url_get(),
+            expiry(doc.blob_url_get_expires()),
+        ))
+    }
+
+    /// The blob URL to which a node's blob can be put
+    ///
+    /// Fails if the server didn't provide a URL.
+    pub fn for_upload(reply: &UploadRequestResponse) -> Result<Self> {
+        if reply.blob_url_put().is_empty() {
+            return Err(Error::protocol(
+                "UploadRequest",
+                format!(
+                    "Blob URL missing for {}",
+                    reply.id().map_or("", NodeId::as_str)
+                ),
+            ));
+        }
+        Ok(Self::new(
+            reply.blob_url_put(),
+            expiry(reply.blob_url_put_expires()),
+        ))
+    }
+
+    /// The URL itself
+    ///
+    /// ```
+    /// # use remsync_api_client::BlobUrl;
+    /// # let url = BlobUrl::new("https://blobs.example/some-blob", None);
+    /// assert_eq!(url.url(), "https://blobs.example/some-blob");
+    /// ```
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// When the URL stops working, if the server said
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// Whether the URL has already expired
+    ///
+    /// A URL with no known expiry is assumed to be good.
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(Duration::from_secs(0))
+    }
+
+    /// Whether the URL will have expired `margin` from now
+    ///
+    /// ```
+    /// # use remsync_api_client::BlobUrl;
+    /// # use std::time::{Duration, SystemTime};
+    /// let soon = SystemTime::now() + Duration::from_secs(10);
+    /// let url = BlobUrl::new("https://blobs.example/some-blob", Some(soon));
+    /// assert!(!url.is_expired());
+    /// assert!(url.expires_within(Duration::from_secs(60)));
+    /// ```
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match self.expires {
+            Some(expires) => expires <= SystemTime::now() + margin,
+            None => false,
+        }
+    }
+}
+
+/// The expiry time of a blob URL, if known
+///
+/// The server sends the zero time (`0001-01-01T00:00:00Z`) or nothing at
+/// all when there is no URL, so anything before the epoch means "unknown".
+fn expiry(expires: &Timestamp) -> Option<SystemTime> {
+    expires
+        .to_system_time()
+        .filter(|expires| *expires > UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expiry_times_are_converted() {
+        let expires: Timestamp = "2019-08-31T15:52:52.186395996Z".parse().unwrap();
+        assert_eq!(
+            expiry(&expires),
+            Some(UNIX_EPOCH + Duration::new(1_567_266_772, 186_395_996))
+        );
+        assert_eq!(expiry(&Timestamp::zero()), None);
+        assert_eq!(expiry(&"".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn expiry_is_checked() {
+        let past = BlobUrl::new("some-url", Some(UNIX_EPOCH + Duration::from_secs(1)));
+        assert!(past.is_expired());
+        assert!(!BlobUrl::new("some-url", None).is_expired());
+    }
+}
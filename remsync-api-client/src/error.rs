@@ -0,0 +1,269 @@
+//! Error type for remsync-api-client
+//!
+//! Every API call in this crate reports failure through [`Error`] so that
+//! callers can distinguish between, for example, a network failure, an
+//! authentication failure, and a version conflict without having to resort
+//! to matching on strings.
+
+use std::fmt;
+
+use hyper::StatusCode;
+use remsync_api_types::NodeId;
+
+/// The result type used throughout remsync-api-client
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors which can occur while talking to the reMarkable cloud APIs
+///
+/// Where an error relates to a particular API call, the `api` field names
+/// that call (e.g. `"UpdateStatus"`) in order to make logs useful.
+#[derive(Debug)]
+pub enum Error {
+    /// A transport level failure such as a refused or reset connection
+    Transport(hyper::Error),
+    /// The request could not be constructed, usually due to a bad URI
+    Request(http::Error),
+    /// The server replied with a non-success HTTP status
+    Status {
+        /// The API which was being called
+        api: &'static str,
+        /// The HTTP status code returned
+        status: StatusCode,
+        /// The body of the response, as (lossily decoded) text
+        body: String,
+    },
+    /// A response body could not be decoded as the expected JSON
+    Json(serde_json::Error),
+    /// A JWT could not be decoded
+    Token(jsonwebtoken::errors::Error),
+    /// A token failed verification, for example because it has expired or
+    /// its signature doesn't match
+    TokenRejected(String),
+    /// The server replied with `Success: false` for a request
+    Server {
+        /// The API which was being called
+        api: &'static str,
+        /// The `Message` returned by the server
+        message: String,
+        /// The `ID` of the node the failure relates to, if known
+        id: Option<NodeId>,
+        /// The `Version` returned by the server
+        version: usize,
+    },
+    /// The server refused an upload, update or delete because the version
+    /// sent did not follow on from the one it holds
+    VersionConflict {
+        /// The API which was being called
+        api: &'static str,
+        /// The `Message` returned by the server
+        message: String,
+        /// The `ID` of the node the conflict relates to, if known
+        id: Option<NodeId>,
+        /// The version of the node which the server holds, if the server
+        /// said
+        server_version: Option<usize>,
+    },
+    /// The server replied with something well formed but unexpected,
+    /// such as the wrong number of entries in a list
+    Protocol {
+        /// The API which was being called
+        api: &'static str,
+        /// A description of what was wrong
+        reason: String,
+    },
+    /// A local I/O error, for example while writing out a blob
+    Io(std::io::Error),
+    /// The client was configured with something unusable, such as an
+    /// unparseable certificate
+    Config(String),
+    /// A signed blob URL had expired by the time we came to use it
+    BlobUrlExpired {
+        /// The API which was being called
+        api: &'static str,
+    },
+    /// The blob store rejected a signed blob URL which had not yet expired,
+    /// usually because it has already been used
+    BlobUrlConsumed {
+        /// The API which was being called
+        api: &'static str,
+        /// The HTTP status code returned
+        status: StatusCode,
+        /// The body of the response, as (lossily decoded) text
+        body: String,
+    },
+    /// A partly fetched blob turned out not to be part of the blob on the
+    /// server, so it was thrown away and the fetch has to start again with
+    /// a fresh blob URL
+    BlobRestart {
+        /// The API which was being called
+        api: &'static str,
+    },
+    /// A transfer was not attempted because one it had to wait for failed
+    DependencyFailed {
+        /// The ID of the node which was not transferred
+        id: NodeId,
+        /// The ID of the node it was waiting for
+        dependency: NodeId,
+    },
+}
+
+impl Error {
+    /// Construct a protocol error for the given API
+    pub(crate) fn protocol<S: Into<String>>(api: &'static str, reason: S) -> Self {
+        Error::Protocol {
+            api,
+            reason: reason.into(),
+        }
+    }
+
+    /// The HTTP status code associated with this error, if there is one
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Status { status, .. } | Error::BlobUrlConsumed { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether the server refused a change because of the version sent
+    pub fn is_version_conflict(&self) -> bool {
+        matches!(self, Error::VersionConflict { .. })
+    }
+
+    /// The server's version of the node, if this is a version conflict
+    /// and the server said what it holds
+    ///
+    /// A sync engine can use this to decide whether to fetch the server's
+    /// copy before trying again.  Not every conflict reply gives the
+    /// version, so for a conflict without one fetch the node to find out,
+    /// as [`StorageSession::conflict_version`](crate::StorageSession::conflict_version)
+    /// does.
+    pub fn conflict_version(&self) -> Option<usize> {
+        match self {
+            Error::VersionConflict { server_version, .. } => *server_version,
+            _ => None,
+        }
+    }
+
+    /// Whether this error indicates the server rejected our credentials
+    pub fn is_unauthorized(&self) -> bool {
+        self.status() == Some(StatusCode::UNAUTHORIZED)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(e) => write!(f, "Transport error: {}", e),
+            Error::Request(e) => write!(f, "Unable to construct request: {}", e),
+            Error::Status { api, status, body } => {
+                write!(f, "API:{}: HTTP status {}", api, status)?;
+                if !body.is_empty() {
+                    write!(f, ": {}", body)?;
+                }
+                Ok(())
+            }
+            Error::Json(e) => write!(f, "Unable to decode JSON: {}", e),
+            Error::Token(e) => write!(f, "Unable to decode token: {}", e),
+            Error::TokenRejected(reason) => write!(f, "Token rejected: {}", reason),
+            Error::Server {
+                api,
+                message,
+                id,
+                version,
+            } => {
+                write!(f, "API:{}: server reported failure", api)?;
+                if let Some(id) = id {
+                    write!(f, " for {}", id)?;
+                }
+                write!(f, " (version {}): {}", version, message)
+            }
+            Error::VersionConflict {
+                api,
+                message,
+                id,
+                server_version,
+            } => {
+                write!(f, "API:{}: version conflict", api)?;
+                if let Some(id) = id {
+                    write!(f, " for {}", id)?;
+                }
+                if let Some(server_version) = server_version {
+                    write!(f, " (server has version {})", server_version)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Protocol { api, reason } => write!(f, "API:{}: {}", api, reason),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Config(reason) => write!(f, "Invalid configuration: {}", reason),
+            Error::BlobUrlExpired { api } => write!(f, "API:{}: blob URL has expired", api),
+            Error::BlobUrlConsumed { api, status, body } => {
+                write!(
+                    f,
+                    "API:{}: blob URL already used (HTTP status {})",
+                    api, status
+                )?;
+                if !body.is_empty() {
+                    write!(f, ": {}", body)?;
+                }
+                Ok(())
+            }
+            Error::BlobRestart { api } => {
+                write!(f, "API:{}: partial blob discarded, fetch must restart", api)
+            }
+            Error::DependencyFailed { id, dependency } => write!(
+                f,
+                "Not transferring {} because {} was not transferred",
+                id, dependency
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Request(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Token(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+impl From<http::Error> for Error {
+    fn from(e: http::Error) -> Self {
+        Error::Request(e)
+    }
+}
+
+impl From<http::uri::InvalidUri> for Error {
+    fn from(e: http::uri::InvalidUri) -> Self {
+        Error::Request(e.into())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for Error {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        Error::Token(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,461 @@
+//! Verifying the JWTs used as device and user tokens
+//!
+//! The rest of this crate only ever peeks at the claims of a token, trusting
+//! the server to reject one which is forged or stale.  A [`TokenVerifier`]
+//! checks a token properly before it's used: the signature against the
+//! issuer's keys, given directly or as a JWKS document, and the `exp` and
+//! `nbf` claims against the clock, allowing for some skew.  That matters
+//! most for a self-hosted deployment, which issues tokens of its own.
+
+use std::time::Duration;
+
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, Validation};
+use remsync_api_types::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// How much clock skew is allowed for by default
+pub const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// A single key in a JWKS document
+///
+/// Only the members needed for signing keys are kept: `oct` keys carry the
+/// secret in `k`, `RSA` keys their modulus and exponent in `n` and `e`,
+/// and `EC` keys their curve and point in `crv`, `x` and `y`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    /// The key type, `oct`, `RSA` or `EC`
+    pub kty: String,
+    /// The ID by which tokens refer to this key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// The algorithm this key is used with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// What this key is for, `sig` for signing
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    /// The secret of an `oct` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k: Option<String>,
+    /// The modulus of an `RSA` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// The public exponent of an `RSA` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// The curve of an `EC` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// The x coordinate of an `EC` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// The y coordinate of an `EC` key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+/// A JWKS document, as published by an issuer
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwks {
+    /// The keys in the set
+    pub keys: Vec<Jwk>,
+}
+
+/// The families of algorithm, each of which wants a different sort of key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyFamily {
+    Hmac,
+    Rsa,
+    Ec,
+}
+
+impl KeyFamily {
+    fn of(alg: Algorithm) -> Self {
+        match alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => KeyFamily::Hmac,
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => KeyFamily::Rsa,
+            Algorithm::ES256 | Algorithm::ES384 => KeyFamily::Ec,
+        }
+    }
+}
+
+/// A key in the form `jsonwebtoken` wants it
+#[derive(Clone, Debug)]
+struct VerifyingKey {
+    kid: Option<String>,
+    /// The only algorithm this key may be used with, if it's restricted
+    alg: Option<Algorithm>,
+    family: KeyFamily,
+    material: Vec<u8>,
+}
+
+/// Checks the signatures and validity periods of tokens
+#[derive(Clone, Debug)]
+pub struct TokenVerifier {
+    keys: Vec<VerifyingKey>,
+    leeway: Duration,
+}
+
+impl Default for TokenVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenVerifier {
+    /// A verifier with no keys yet, allowing [`DEFAULT_LEEWAY`] of skew
+    pub fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            leeway: DEFAULT_LEEWAY,
+        }
+    }
+
+    fn with_key(
+        mut self,
+        kid: Option<&str>,
+        alg: Option<Algorithm>,
+        family: KeyFamily,
+        material: Vec<u8>,
+    ) -> Self {
+        self.keys.push(VerifyingKey {
+            kid: kid.map(str::to_owned),
+            alg,
+            family,
+            material,
+        });
+        self
+    }
+
+    /// Accept tokens signed with the shared secret `secret` (`HS256` etc.)
+    pub fn with_hmac_secret(self, secret: &[u8]) -> Self {
+        self.with_key(None, None, KeyFamily::Hmac, secret.to_vec())
+    }
+
+    /// Accept tokens signed by the RSA key whose public half is `der`, a
+    /// DER encoded PKCS#1 `RSAPublicKey` (`RS256` etc.)
+    pub fn with_rsa_public_key(self, der: &[u8]) -> Self {
+        self.with_key(None, None, KeyFamily::Rsa, der.to_vec())
+    }
+
+    /// Accept tokens signed by the ECDSA key whose public half is `point`,
+    /// an uncompressed curve point (`ES256` etc.)
+    pub fn with_ec_public_key(self, point: &[u8]) -> Self {
+        self.with_key(None, None, KeyFamily::Ec, point.to_vec())
+    }
+
+    /// Accept tokens signed by any of the keys in `jwks`
+    ///
+    /// Keys which aren't for signing are skipped, anything else which
+    /// can't be used is an error.  A key which names its `alg` only
+    /// accepts tokens signed with that algorithm.
+    pub fn with_jwks(mut self, jwks: &Jwks) -> Result<Self> {
+        for jwk in &jwks.keys {
+            if jwk.key_use.as_ref().is_some_and(|u| u != "sig") {
+                continue;
+            }
+            let (family, material) = jwk_material(jwk)?;
+            let alg = jwk_alg(jwk, family)?;
+            self = self.with_key(jwk.kid.as_deref(), alg, family, material);
+        }
+        Ok(self)
+    }
+
+    /// Accept tokens signed by any of the keys in a JWKS document
+    pub fn with_jwks_json(self, json: &[u8]) -> Result<Self> {
+        let jwks: Jwks = serde_json::from_slice(json)?;
+        self.with_jwks(&jwks)
+    }
+
+    /// Allow for `leeway` of clock skew when checking validity periods
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Verify a device token, returning its claims
+    ///
+    /// Device tokens don't expire, so only `nbf` is checked.
+    pub fn verify_device_token(&self, token: &str) -> Result<DeviceToken> {
+        self.verify(token, false)
+    }
+
+    /// Verify a user token, returning its claims
+    pub fn verify_user_token(&self, token: &str) -> Result<UserToken> {
+        self.verify(token, true)
+    }
+
+    /// Verify any token, returning its claims
+    ///
+    /// The signature must match one of our keys of the right sort, and one
+    /// named by the token's `kid` if it has one.  A key restricted to one
+    /// algorithm is only tried if that's the token's `alg`.  If `check_expiry` is set
+    /// then the token must have an `exp` claim, and it must not have
+    /// passed.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str, check_expiry: bool) -> Result<T> {
+        let header = jsonwebtoken::decode_header(token).map_err(rejection)?;
+        let family = KeyFamily::of(header.alg);
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway.as_secs() as i64;
+        validation.validate_exp = check_expiry;
+        validation.validate_nbf = true;
+
+        let candidates = self.keys.iter().filter(|key| {
+            key.family == family
+                && key.alg.is_none_or(|alg| alg == header.alg)
+                && match (&header.kid, &key.kid) {
+                    (Some(wanted), Some(kid)) => wanted == kid,
+                    _ => true,
+                }
+        });
+        let mut last = None;
+        for key in candidates {
+            match jsonwebtoken::decode::<T>(token, &key.material, &validation) {
+                Ok(data) => return Ok(data.claims),
+                Err(e) => match e.kind() {
+                    // Another key may yet match
+                    ErrorKind::InvalidSignature
+                    | ErrorKind::InvalidRsaKey
+                    | ErrorKind::InvalidEcdsaKey => last = Some(e),
+                    _ => return Err(rejection(e)),
+                },
+            }
+        }
+        match last {
+            Some(e) => Err(rejection(e)),
+            None => Err(Error::TokenRejected(match header.kid {
+                Some(kid) => format!("no {:?} key with ID {:?}", header.alg, kid),
+                None => format!("no {:?} key to check it with", header.alg),
+            })),
+        }
+    }
+}
+
+/// Explain why `jsonwebtoken` didn't like a token
+fn rejection(e: jsonwebtoken::errors::Error) -> Error {
+    let reason = match e.kind() {
+        ErrorKind::InvalidSignature => "signature does not match",
+        ErrorKind::ExpiredSignature => "token has expired",
+        ErrorKind::ImmatureSignature => "token is not valid yet",
+        ErrorKind::InvalidAlgorithm => "unexpected signing algorithm",
+        ErrorKind::InvalidRsaKey => "unusable RSA key",
+        ErrorKind::InvalidEcdsaKey => "unusable ECDSA key",
+        _ => return Error::Token(e),
+    };
+    Error::TokenRejected(reason.to_owned())
+}
+
+/// Decode a base64url member of a JWK
+fn jwk_member(jwk: &Jwk, name: &str, value: &Option<String>) -> Result<Vec<u8>> {
+    let value = value
+        .as_ref()
+        .ok_or_else(|| Error::Config(format!("{} key {:?} has no {:?}", jwk.kty, jwk.kid, name)))?;
+    base64::decode_config(value, base64::URL_SAFE_NO_PAD).map_err(|e| {
+        Error::Config(format!(
+            "{} key {:?} has a bad {:?}: {}",
+            jwk.kty, jwk.kid, name, e
+        ))
+    })
+}
+
+/// Turn a JWK into key material for `jsonwebtoken`
+fn jwk_material(jwk: &Jwk) -> Result<(KeyFamily, Vec<u8>)> {
+    match jwk.kty.as_str() {
+        "oct" => Ok((KeyFamily::Hmac, jwk_member(jwk, "k", &jwk.k)?)),
+        "RSA" => {
+            let n = jwk_member(jwk, "n", &jwk.n)?;
+            let e = jwk_member(jwk, "e", &jwk.e)?;
+            Ok((KeyFamily::Rsa, rsa_public_key_der(&n, &e)))
+        }
+        "EC" => {
+            let x = jwk_member(jwk, "x", &jwk.x)?;
+            let y = jwk_member(jwk, "y", &jwk.y)?;
+            let mut point = Vec::with_capacity(1 + x.len() + y.len());
+            point.push(0x04);
+            point.extend_from_slice(&x);
+            point.extend_from_slice(&y);
+            Ok((KeyFamily::Ec, point))
+        }
+        other => Err(Error::Config(format!(
+            "Unsupported key type {:?} for key {:?}",
+            other, jwk.kid
+        ))),
+    }
+}
+
+/// The algorithm a JWK is restricted to, if it names one
+///
+/// The algorithm must be one for the sort of key it is.
+fn jwk_alg(jwk: &Jwk, family: KeyFamily) -> Result<Option<Algorithm>> {
+    let name = match &jwk.alg {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    match name.parse::<Algorithm>() {
+        Ok(alg) if KeyFamily::of(alg) == family => Ok(Some(alg)),
+        _ => Err(Error::Config(format!(
+            "{} key {:?} can't be used with {:?}",
+            jwk.kty, jwk.kid, name
+        ))),
+    }
+}
+
+/// Append a DER length
+fn der_length(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .iter()
+            .copied()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+}
+
+/// Append a DER INTEGER holding the unsigned big-endian `value`
+fn der_integer(out: &mut Vec<u8>, value: &[u8]) {
+    let value: Vec<u8> = value.iter().copied().skip_while(|b| *b == 0).collect();
+    let pad = value.first().is_none_or(|b| b & 0x80 != 0);
+    out.push(0x02);
+    der_length(out, value.len() + pad as usize);
+    if pad {
+        out.push(0);
+    }
+    out.extend_from_slice(&value);
+}
+
+/// The DER encoded PKCS#1 `RSAPublicKey` with modulus `n` and exponent `e`
+fn rsa_public_key_der(n: &[u8], e: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    der_integer(&mut body, n);
+    der_integer(&mut body, e);
+    let mut out = vec![0x30];
+    der_length(&mut out, body.len());
+    out.extend_from_slice(&body);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil::*;
+    use jsonwebtoken::{encode, Header};
+    use serde_json::json;
+
+    const JWKS: &[u8] = include_bytes!("../testdata/jwks.json");
+    const RSA_KEY: &[u8] = include_bytes!("../testdata/rsa-private.der");
+    const EC_KEY: &[u8] = include_bytes!("../testdata/ec-private.p8");
+
+    fn device_token(alg: Algorithm, kid: Option<&str>, key: &[u8], nbf: u64) -> String {
+        let mut header = Header::new(alg);
+        header.kid = kid.map(str::to_owned);
+        let claims = DeviceToken::new(
+            "auth0|some-user",
+            "desktop-linux",
+            "some-device-id",
+            nbf,
+            "rM WebApp",
+            "some-token-id",
+            nbf,
+            "rM Device Token",
+        );
+        encode(&header, &claims, key).unwrap()
+    }
+
+    #[test]
+    fn shared_secrets_are_checked() {
+        let verifier = TokenVerifier::new().with_hmac_secret(b"secret");
+        let token = user_token(now_epoch_secs() + 3600);
+        let claims = verifier.verify_user_token(&token).unwrap();
+        assert_eq!(claims.expires_at(), now_epoch_secs() + 3600);
+        let wrong = TokenVerifier::new().with_hmac_secret(b"guess");
+        match wrong.verify_user_token(&token) {
+            Err(Error::TokenRejected(reason)) => assert_eq!(reason, "signature does not match"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validity_allows_for_skew() {
+        let verifier = TokenVerifier::new().with_hmac_secret(b"secret");
+        assert!(verifier
+            .verify_user_token(&user_token(now_epoch_secs() - 30))
+            .is_ok());
+        match verifier.verify_user_token(&user_token(now_epoch_secs() - 120)) {
+            Err(Error::TokenRejected(reason)) => assert_eq!(reason, "token has expired"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let early = device_token(Algorithm::HS256, None, b"secret", now_epoch_secs() + 3600);
+        match verifier.verify_device_token(&early) {
+            Err(Error::TokenRejected(reason)) => assert_eq!(reason, "token is not valid yet"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let device = device_token(Algorithm::HS256, None, b"secret", now_epoch_secs());
+        assert!(verifier.verify_device_token(&device).is_ok());
+        // Device tokens carry no expiry, so can't pass as user tokens
+        assert!(verifier.verify_user_token(&device).is_err());
+    }
+
+    #[test]
+    fn jwks_keys_are_used() {
+        let verifier = TokenVerifier::new().with_jwks_json(JWKS).unwrap();
+        let rsa = device_token(
+            Algorithm::RS256,
+            Some("rsa-test"),
+            RSA_KEY,
+            now_epoch_secs(),
+        );
+        let claims = verifier.verify_device_token(&rsa).unwrap();
+        assert_eq!(claims.device_id(), "some-device-id");
+        let ec = device_token(Algorithm::ES256, None, EC_KEY, now_epoch_secs());
+        assert!(verifier.verify_device_token(&ec).is_ok());
+        let unknown = device_token(Algorithm::RS256, Some("other"), RSA_KEY, now_epoch_secs());
+        assert!(matches!(
+            verifier.verify_device_token(&unknown),
+            Err(Error::TokenRejected(_))
+        ));
+        // Nor will an HMAC signature made with a public key do
+        let forged = device_token(Algorithm::HS256, None, b"public", now_epoch_secs());
+        assert!(verifier.verify_device_token(&forged).is_err());
+    }
+
+    #[test]
+    fn jwks_algorithms_are_enforced() {
+        let jwks = json!({"keys": [{"kty": "oct", "alg": "HS256", "k": "c2VjcmV0"}]});
+        let jwks = serde_json::to_vec(&jwks).unwrap();
+        let verifier = TokenVerifier::new().with_jwks_json(&jwks).unwrap();
+        let hs256 = device_token(Algorithm::HS256, None, b"secret", now_epoch_secs());
+        assert!(verifier.verify_device_token(&hs256).is_ok());
+        let hs512 = device_token(Algorithm::HS512, None, b"secret", now_epoch_secs());
+        match verifier.verify_device_token(&hs512) {
+            Err(Error::TokenRejected(reason)) => {
+                assert_eq!(reason, "no HS512 key to check it with")
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unusable_jwks_are_refused() {
+        let jwks = json!({"keys": [{"kty": "RSA", "kid": "broken", "n": "AQAB"}]});
+        let jwks = serde_json::to_vec(&jwks).unwrap();
+        assert!(matches!(
+            TokenVerifier::new().with_jwks_json(&jwks),
+            Err(Error::Config(_))
+        ));
+        let jwks = json!({"keys": [{"kty": "oct", "alg": "RS256", "k": "c2VjcmV0"}]});
+        let jwks = serde_json::to_vec(&jwks).unwrap();
+        assert!(matches!(
+            TokenVerifier::new().with_jwks_json(&jwks),
+            Err(Error::Config(_))
+        ));
+    }
+}
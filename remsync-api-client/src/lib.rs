@@ -2,13 +2,40 @@
 
 pub(crate) mod util;
 
+mod backoff;
+mod blob_url;
+#[cfg(feature = "tls")]
+pub mod connector;
+mod error;
+pub mod jwt;
 pub mod ll;
+#[cfg(any(test, feature = "in-memory"))]
+pub mod memory;
+pub mod mint;
+mod notifications;
+mod profile;
+mod progress;
+pub mod recording;
+mod retry;
+mod session;
+#[cfg(test)]
+mod testutil;
+mod tokencache;
+mod transfers;
+mod transport;
+mod ws;
 
-/// Generic error used because I'm too lazy to make a good one
-type GenericError = std::boxed::Box<dyn std::error::Error + Send + Sync>;
-
-/// Generic result used because I'm too lazy to make a good one
-type GenericResult<T> = std::result::Result<T, GenericError>;
+pub use backoff::Backoff;
+pub use blob_url::BlobUrl;
+pub use error::{Error, Result};
+pub use notifications::{NotificationSocket, NotificationStream};
+pub use profile::{Profile, SELF_HOSTED_DOMAIN};
+pub use progress::Progress;
+pub use retry::RetryPolicy;
+pub use session::StorageSession;
+pub use tokencache::TokenCache;
+pub use transfers::{Transfer, TransferResults, TransferScheduler};
+pub use transport::{Transport, TransportFuture};
 
 // Reexport hyper and http here to ensure that we always use the same
 // since for now we're getting it via git
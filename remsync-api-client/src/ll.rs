@@ -1,17 +1,27 @@
 //! Low level API implementations for remsync-api-client
 
-use hyper::{client::connect::Connect, Body, Client, Request, Uri};
+use std::io::SeekFrom;
+use std::path::Path;
+
+use futures::future;
+use hyper::{Body, Request, Response, StatusCode, Uri};
 use remsync_api_types::*;
+use serde::de::DeserializeOwned;
+use tokio::codec::Framed;
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 
-use crate::{util::*, GenericResult};
+use crate::notifications::NotificationSocket;
+use crate::ws::{self, Role, WebSocketCodec};
+use crate::{util::*, BlobUrl, Error, Progress, Result, Transport};
 
-pub async fn auth_get_device_bearer<C>(
-    client: &Client<C, Body>,
+pub async fn auth_get_device_bearer<T>(
+    client: &T,
     base: &Uri,
     req: &DeviceTokenRequest,
-) -> GenericResult<String>
+) -> Result<String>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
     let body = serde_json::to_string(req)?;
     let request = Request::builder()
@@ -20,24 +30,16 @@ where
         .body(Body::from(body))?;
 
     let response = client.request(request).await?;
-
-    if !response.status().is_success() {
-        // Failed to get something back
-        return Err(format!("API:GetDeviceBearer:{:?}", response).into());
-    }
+    let response = check_status("GetDeviceBearer", response).await?;
 
     // The body if there is one, is our new bearer token, so return it
     let ret = hoover_body_to_vec(response.into_body()).await?;
-    Ok(String::from_utf8(ret)?)
+    String::from_utf8(ret).map_err(|_| Error::protocol("GetDeviceBearer", "Token is not UTF-8"))
 }
 
-pub async fn auth_get_user_bearer<C>(
-    client: &Client<C, Body>,
-    base: &Uri,
-    device_bearer: &str,
-) -> GenericResult<String>
+pub async fn auth_get_user_bearer<T>(client: &T, base: &Uri, device_bearer: &str) -> Result<String>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
     let request = Request::builder()
         .header("Authorization", format!("Bearer {}", device_bearer))
@@ -46,121 +48,272 @@ where
         .uri(catenate_url_path(base, "/token/json/2/user/new")?)
         .body(Body::empty())?;
     let response = client.request(request).await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API:GetUserBearer:{:?}", response).into());
-    }
+    let response = check_status("GetUserBearer", response).await?;
 
     // The body if there is one, is our new bearer token, so return it
     let ret = hoover_body_to_vec(response.into_body()).await?;
-    Ok(String::from_utf8(ret)?)
+    String::from_utf8(ret).map_err(|_| Error::protocol("GetUserBearer", "Token is not UTF-8"))
+}
+
+/// The discovery environment used by the official clients
+pub const DEFAULT_ENVIRONMENT: &str = "production";
+
+/// The `apiVer` used by the official clients to discover document storage
+pub const STORAGE_API_VERSION: u32 = 2;
+
+/// The `apiVer` used by the official clients to discover notifications
+pub const NOTIFICATIONS_API_VERSION: u32 = 1;
+
+/// Extract the discovery group (the auth0 user id) from a user token
+fn discovery_group(user_bearer: &str) -> Result<String> {
+    let token = jsonwebtoken::dangerous_unsafe_decode::<UserToken>(user_bearer)?;
+    Ok(token.claims.auth0_profile().user_id().to_owned())
 }
 
-pub async fn discover_storage_service<C>(
-    client: &Client<C, Body>,
+async fn discover_service<T>(
+    client: &T,
     base: &Uri,
     user_bearer: &str,
-) -> GenericResult<String>
+    api: &'static str,
+    service: &str,
+    environment: &str,
+    api_ver: u32,
+) -> Result<String>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
-    let token = jsonwebtoken::dangerous_unsafe_decode::<UserToken>(user_bearer)?;
-    let group = token.claims.auth0_profile().user_id();
-    let group = percent_encoding::utf8_percent_encode(&group, &percent_encoding::NON_ALPHANUMERIC);
+    let group = discovery_group(user_bearer)?;
+    let group = percent_encoding::utf8_percent_encode(&group, percent_encoding::NON_ALPHANUMERIC);
+    let environment =
+        percent_encoding::utf8_percent_encode(environment, percent_encoding::NON_ALPHANUMERIC);
     let request = Request::builder()
         .header("Authorization", format!("Bearer {}", user_bearer))
         .method("GET")
         .uri(catenate_url_path(
             base,
             &format!(
-                "/service/json/1/document-storage?environment=production&apiVer=2&group={}",
-                group
+                "/service/json/1/{}?environment={}&apiVer={}&group={}",
+                service, environment, api_ver, group
             ),
         )?)
         .body(Body::empty())?;
     let response = client.request(request).await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API:DiscoverStorageService:{:?}", response).into());
-    }
+    let response = check_status(api, response).await?;
 
     let ret = hoover_body_to_vec(response.into_body()).await?;
     let ret: DiscoveryResponse = serde_json::from_slice(&ret)?;
     if ret.status() != "OK" {
-        return Err("Unknown error during discovery".into());
+        return Err(Error::protocol(
+            api,
+            format!("Discovery status was {:?}", ret.status()),
+        ));
     }
     Ok(ret.into_host())
 }
 
-pub async fn storage_fetch_all_docs<C>(
-    client: &Client<C, Body>,
+pub async fn discover_storage_service<T>(
+    client: &T,
+    base: &Uri,
+    user_bearer: &str,
+    environment: &str,
+    api_ver: u32,
+) -> Result<String>
+where
+    T: Transport + ?Sized,
+{
+    discover_service(
+        client,
+        base,
+        user_bearer,
+        "DiscoverStorageService",
+        "document-storage",
+        environment,
+        api_ver,
+    )
+    .await
+}
+
+pub async fn discover_notification_service<T>(
+    client: &T,
+    base: &Uri,
+    user_bearer: &str,
+    environment: &str,
+    api_ver: u32,
+) -> Result<String>
+where
+    T: Transport + ?Sized,
+{
+    discover_service(
+        client,
+        base,
+        user_bearer,
+        "DiscoverNotificationService",
+        "notifications",
+        environment,
+        api_ver,
+    )
+    .await
+}
+
+pub async fn notifications_connect<T>(
+    client: &T,
     base: &Uri,
     user_token: &str,
-) -> GenericResult<Vec<DocsResponse>>
+) -> Result<NotificationSocket>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
+    let key = ws::generate_key();
     let request = Request::builder()
         .header("Authorization", format!("Bearer {}", user_token))
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", key.as_str())
         .method("GET")
-        .uri(catenate_url_path(base, "/document-storage/json/2/docs")?)
+        .uri(catenate_url_path(base, "/notifications/ws/json/1")?)
         .body(Body::empty())?;
     let response = client.request(request).await?;
+    if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+        // Anything other than a switch is a failure, even if it's a 200
+        let response = check_status(ws::API, response).await?;
+        return Err(Error::protocol(
+            ws::API,
+            format!("Expected a websocket upgrade, got {}", response.status()),
+        ));
+    }
 
-    if !response.status().is_success() {
-        return Err(format!("API:GetDocsList:{:?}", response).into());
+    let accept = response
+        .headers()
+        .get("Sec-WebSocket-Accept")
+        .and_then(|value| value.to_str().ok());
+    if accept != Some(ws::accept_key(&key).as_str()) {
+        return Err(Error::protocol(
+            ws::API,
+            "Bad Sec-WebSocket-Accept from server",
+        ));
     }
 
+    let upgraded = response.into_body().on_upgrade().await?;
+    Ok(NotificationSocket::new(Framed::new(
+        upgraded,
+        WebSocketCodec::new(Role::Client),
+    )))
+}
+
+/// List every node
+///
+/// The IDs in the reply must be UUIDs unless `lenient` is set
+pub async fn storage_fetch_all_docs<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    lenient: bool,
+) -> Result<Vec<DocsResponse>>
+where
+    T: Transport + ?Sized,
+{
+    let request = Request::builder()
+        .header("Authorization", format!("Bearer {}", user_token))
+        .method("GET")
+        .uri(catenate_url_path(base, "/document-storage/json/2/docs")?)
+        .body(Body::empty())?;
+    let response = client.request(request).await?;
+    let response = check_status("GetDocsList", response).await?;
+
     // The body is a JSON list of document nodes
     let ret = hoover_body_to_vec(response.into_body()).await?;
-    Ok(serde_json::from_slice(&ret)?)
+    Ok(from_received_slice(&ret, lenient)?)
 }
 
-pub async fn storage_fetch_blob<C>(
-    client: &Client<C, Body>,
+/// Retrieve the metadata for a single node, without minting a blob URL
+///
+/// Returns `None` if the server has no such node.  The IDs in the reply
+/// must be UUIDs unless `lenient` is set
+pub async fn storage_fetch_doc<T>(
+    client: &T,
     base: &Uri,
     user_token: &str,
-    id: &str,
-    output: &mut dyn std::io::Write,
-) -> GenericResult<usize>
+    id: &NodeId,
+    lenient: bool,
+) -> Result<Option<DocsResponse>>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
-    let doc = percent_encoding::utf8_percent_encode(id, &percent_encoding::NON_ALPHANUMERIC);
+    let doc =
+        percent_encoding::utf8_percent_encode(id.as_str(), percent_encoding::NON_ALPHANUMERIC);
     let request = Request::builder()
         .header("Authorization", format!("Bearer {}", user_token))
         .method("GET")
         .uri(catenate_url_path(
             base,
-            &format!("/document-storage/json/2/docs?withBlob=1&doc={}", doc),
+            &format!("/document-storage/json/2/docs?doc={}", doc),
         )?)
         .body(Body::empty())?;
     let response = client.request(request).await?;
+    let response = check_status("GetDocsList", response).await?;
 
-    if !response.status().is_success() {
-        return Err(format!("API:GetDocsList:{:?}", response).into());
+    // The body is a JSON list of document nodes, empty if there's no match
+    let docs = hoover_body_to_vec(response.into_body()).await?;
+    let docs: Vec<DocsResponse> = from_received_slice(&docs, lenient)?;
+    if docs.is_empty() {
+        return Ok(None);
     }
+    single_reply("GetDocsList", docs).map(Some)
+}
+
+/// Retrieve the signed URL from which the blob for a node can be fetched
+///
+/// Each call mints a fresh URL, so only call this when about to download.
+/// The IDs in the reply must be UUIDs unless `lenient` is set
+pub async fn storage_fetch_blob_url<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    id: &NodeId,
+    lenient: bool,
+) -> Result<BlobUrl>
+where
+    T: Transport + ?Sized,
+{
+    let doc =
+        percent_encoding::utf8_percent_encode(id.as_str(), percent_encoding::NON_ALPHANUMERIC);
+    let request = Request::builder()
+        .header("Authorization", format!("Bearer {}", user_token))
+        .method("GET")
+        .uri(catenate_url_path(
+            base,
+            &format!("/document-storage/json/2/docs?withBlob=1&doc={}", doc),
+        )?)
+        .body(Body::empty())?;
+    let response = client.request(request).await?;
+    let response = check_status("GetDocsList", response).await?;
 
     // The body is a JSON list of document nodes
     let docs = hoover_body_to_vec(response.into_body()).await?;
-    let docs: Vec<DocsResponse> = serde_json::from_slice(&docs)?;
-    if docs.len() != 1 {
-        return Err(format!("API:GetDocsList: Expected 1, got {} documents", docs.len()).into());
-    }
-
-    if docs[0].blob_url_get().is_empty() {
-        return Err(format!("API:GetDocsList: Blob URL missing: {:?}", docs[0]).into());
-    }
+    let docs: Vec<DocsResponse> = from_received_slice(&docs, lenient)?;
+    let doc = single_reply("GetDocsList", docs)?;
+    BlobUrl::for_download(&doc)
+}
 
+pub async fn storage_fetch_blob<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    id: &NodeId,
+    lenient: bool,
+    output: &mut dyn std::io::Write,
+) -> Result<usize>
+where
+    T: Transport + ?Sized,
+{
+    let blob_url_get = storage_fetch_blob_url(client, base, user_token, id, lenient).await?;
     let request = Request::builder()
         .method("GET")
-        .uri(docs[0].blob_url_get())
+        .uri(blob_url_get.url())
         .body(Body::empty())?;
     let response = client.request(request).await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API:GetBlob:{:?}", response).into());
-    }
+    let response = check_blob_status("GetBlob", &blob_url_get, response).await?;
 
     let mut body = response.into_body();
     let mut written = 0;
@@ -173,147 +326,491 @@ where
     Ok(written)
 }
 
-pub async fn storage_delete_doc<C>(
-    client: &Client<C, Body>,
+/// Fetch a blob from the URL given by
+/// [`storage_fetch_blob_url`], writing it to `output`
+///
+/// The `progress` callback is called as each chunk arrives.  If the server
+/// said how long the blob is then the length is checked once the body is
+/// complete.  An expired URL is not even tried.  Returns the number of
+/// bytes written
+pub async fn storage_get_blob_to<T, W>(
+    client: &T,
+    blob_url_get: &BlobUrl,
+    output: W,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<u64>
+where
+    T: Transport + ?Sized,
+    W: AsyncWrite + Unpin,
+{
+    if blob_url_get.is_expired() {
+        return Err(Error::BlobUrlExpired { api: "GetBlob" });
+    }
+    let request = Request::builder()
+        .method("GET")
+        .uri(blob_url_get.url())
+        .body(Body::empty())?;
+    let response = client.request(request).await?;
+    let response = check_blob_status("GetBlob", blob_url_get, response).await?;
+    let total = content_length("GetBlob", &response)?;
+    write_blob_body(response, output, 0, total, progress).await
+}
+
+/// Fetch a blob from the URL given by [`storage_fetch_blob_url`] into the
+/// file at `path`, resuming from whatever the file already holds
+///
+/// If the file isn't empty, only the rest of the blob is requested.  Blob
+/// hosts which don't support range requests send the whole blob instead,
+/// in which case the file is started again.  The file must only ever have
+/// held a prefix of this same blob.  If the file turns out not to fit the
+/// blob it is emptied and [`Error::BlobRestart`] is returned, since the
+/// blob URL may not be usable again.  Returns the size of the whole blob,
+/// along with the size the blob host reported for it, if it did
+pub async fn storage_resume_blob_file<T>(
+    client: &T,
+    blob_url_get: &BlobUrl,
+    path: &Path,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(u64, Option<u64>)>
+where
+    T: Transport + ?Sized,
+{
+    if blob_url_get.is_expired() {
+        return Err(Error::BlobUrlExpired { api: "GetBlob" });
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .await?;
+    let offset = file.metadata().await?.len();
+
+    let mut request = Request::builder();
+    request.method("GET").uri(blob_url_get.url());
+    if offset > 0 {
+        request.header("Range", format!("bytes={}-", offset));
+    }
+    let response = client.request(request.body(Body::empty())?).await?;
+
+    let (start, total) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            let (start, total) = content_range("GetBlob", &response)?;
+            if start != Some(offset) {
+                return Err(Error::protocol(
+                    "GetBlob",
+                    format!("Asked for bytes from {}, got {:?}", offset, start),
+                ));
+            }
+            (offset, total)
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            // Either we already have the whole blob, or the file is longer
+            // than the blob and so can't be part of it
+            let (_, total) = content_range("GetBlob", &response)?;
+            if total == Some(offset) {
+                progress(Progress::new(offset, total));
+                return Ok((offset, total));
+            }
+            file.set_len(0).await?;
+            return Err(Error::BlobRestart { api: "GetBlob" });
+        }
+        _ => {
+            // The host ignored the range, so we're getting the whole blob
+            let response = check_blob_status("GetBlob", blob_url_get, response).await?;
+            file.set_len(0).await?;
+            let total = content_length("GetBlob", &response)?;
+            let size = write_blob_body(response, BufWriter::new(file), 0, total, progress).await?;
+            return Ok((size, total));
+        }
+    };
+    file.seek(SeekFrom::Start(start)).await?;
+    let size = write_blob_body(response, BufWriter::new(file), start, total, progress).await?;
+    Ok((size, total))
+}
+
+/// The `Content-Length` of a response, if it gave one
+fn content_length(api: &'static str, response: &Response<Body>) -> Result<Option<u64>> {
+    match response.headers().get("Content-Length") {
+        Some(value) => Ok(Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .ok_or_else(|| Error::protocol(api, "Unparseable Content-Length"))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// The first byte and total size from the `Content-Range` of a response
+///
+/// Either may be missing, as in `bytes */1000` or `bytes 0-99/*`.
+fn content_range(
+    api: &'static str,
+    response: &Response<Body>,
+) -> Result<(Option<u64>, Option<u64>)> {
+    let bad = || Error::protocol(api, "Unparseable Content-Range");
+    let value = response
+        .headers()
+        .get("Content-Range")
+        .ok_or_else(|| Error::protocol(api, "Missing Content-Range"))?
+        .to_str()
+        .map_err(|_| bad())?;
+    let mut parts = value.strip_prefix("bytes ").ok_or_else(bad)?.splitn(2, '/');
+    let range = parts.next().ok_or_else(bad)?;
+    let total = parts.next().ok_or_else(bad)?;
+    let start = match range {
+        "*" => None,
+        range => Some(
+            range
+                .split('-')
+                .next()
+                .and_then(|start| start.parse().ok())
+                .ok_or_else(bad)?,
+        ),
+    };
+    let total = match total {
+        "*" => None,
+        total => Some(total.parse().map_err(|_| bad())?),
+    };
+    Ok((start, total))
+}
+
+/// Write the body of a blob fetch to `output`, given that it starts at
+/// byte `start` of the blob
+///
+/// If the size of the whole blob is known, it is checked once the body is
+/// complete.  Returns the number of bytes of the blob we now have
+async fn write_blob_body<W>(
+    response: Response<Body>,
+    mut output: W,
+    start: u64,
+    total: Option<u64>,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<u64>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut body = response.into_body();
+    let mut have = start;
+    progress(Progress::new(have, total));
+    while let Some(next) = body.next().await {
+        let chunk = next?;
+        output.write_all(&chunk).await?;
+        have += chunk.len() as u64;
+        progress(Progress::new(have, total));
+    }
+    output.flush().await?;
+
+    match total {
+        Some(total) if total != have => Err(Error::protocol(
+            "GetBlob",
+            format!("Expected {} bytes, got {}", total, have),
+        )),
+        _ => Ok(have),
+    }
+}
+
+/// Fetch the blob for a node, writing it to `output`
+///
+/// This is [`storage_fetch_blob_url`] followed by [`storage_get_blob_to`].
+/// Returns the number of bytes written
+pub async fn storage_fetch_blob_to<T, W>(
+    client: &T,
     base: &Uri,
     user_token: &str,
-    id: &str,
-    version: usize,
-) -> GenericResult<()>
+    id: &NodeId,
+    lenient: bool,
+    output: W,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<u64>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
+    W: AsyncWrite + Unpin,
 {
-    let req = DeleteRequest::new(id, version);
+    let blob_url_get = storage_fetch_blob_url(client, base, user_token, id, lenient).await?;
+    storage_get_blob_to(client, &blob_url_get, output, progress).await
+}
+
+/// Send a batch of requests to one of the storage API's list endpoints
+async fn storage_put_batch<T, R>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    api: &'static str,
+    path: &str,
+    body: String,
+    ids: &[&NodeId],
+) -> Result<Vec<Result<R>>>
+where
+    T: Transport + ?Sized,
+    R: ServerReply + DeserializeOwned + ReceivedIds,
+{
+    // There's no point asking the server to do nothing
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
     let request = Request::builder()
         .method("PUT")
         .header("Authorization", format!("Bearer {}", user_token))
-        .uri(catenate_url_path(base, "/document-storage/json/2/delete")?)
-        .body(Body::from(serde_json::to_string(&[&req])?))?;
+        .uri(catenate_url_path(base, path)?)
+        .body(Body::from(body))?;
     let response = client.request(request).await?;
+    let response = check_status(api, response).await?;
 
-    if !response.status().is_success() {
-        return Err(format!("API:DeleteDoc:{:?}", response).into());
-    }
-
+    // Each reply's ID must be the one sent, so it's checked as strictly as
+    // the caller checked that
     let ret = hoover_body_to_vec(response.into_body()).await?;
-    let ret: Vec<DeleteResponse> = serde_json::from_slice(&ret)?;
-    if ret.len() != 1 {
-        return Err(format!("API:DeleteDoc:{} responses", ret.len()).into());
-    }
-    let ret = &ret[0];
-    if !ret.success() {
-        return Err(format!("API:DeleteDoc:{}", ret.message()).into());
-    }
+    let ret: Vec<R> = from_received_slice(&ret, true)?;
+    batch_replies(api, ids, ret)
+}
+
+pub async fn storage_delete_docs<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    reqs: &[DeleteRequest],
+) -> Result<Vec<Result<DeleteResponse>>>
+where
+    T: Transport + ?Sized,
+{
+    let ids: Vec<&NodeId> = reqs.iter().map(DeleteRequest::id).collect();
+    storage_put_batch(
+        client,
+        base,
+        user_token,
+        "DeleteDoc",
+        "/document-storage/json/2/delete",
+        serde_json::to_string(reqs)?,
+        &ids,
+    )
+    .await
+}
+
+pub async fn storage_delete_doc<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    id: &NodeId,
+    version: usize,
+) -> Result<()>
+where
+    T: Transport + ?Sized,
+{
+    let req = DeleteRequest::new(id.clone(), version);
+    let ret = storage_delete_docs(client, base, user_token, &[req]).await?;
+    single_result(ret)?;
     Ok(())
 }
 
-pub async fn storage_update_doc<C>(
-    client: &Client<C, Body>,
+pub async fn storage_update_docs<T>(
+    client: &T,
     base: &Uri,
     user_token: &str,
-    id: &str,
+    reqs: &[UpdateStatusRequest],
+) -> Result<Vec<Result<UpdateStatusResponse>>>
+where
+    T: Transport + ?Sized,
+{
+    let ids: Vec<&NodeId> = reqs.iter().map(UpdateStatusRequest::id).collect();
+    storage_put_batch(
+        client,
+        base,
+        user_token,
+        "UpdateStatus",
+        "/document-storage/json/2/upload/update-status",
+        serde_json::to_string(reqs)?,
+        &ids,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn storage_update_doc<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    id: &NodeId,
     version: usize,
-    parent: &str,
-    node_type: NodeType,
+    parent: &ParentRef,
+    node_type: &NodeType,
     bookmarked: bool,
     current_page: usize,
     name: &str,
-    modified_client: &str,
-) -> GenericResult<()>
+    modified_client: &Timestamp,
+) -> Result<()>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
     let req = UpdateStatusRequest::new(
-        id,
-        parent,
-        node_type,
+        id.clone(),
+        parent.clone(),
+        node_type.clone(),
         version,
         bookmarked,
         current_page,
         name,
         modified_client,
     );
+    let ret = storage_update_docs(client, base, user_token, &[req]).await?;
+    single_result(ret)?;
+    Ok(())
+}
 
+pub async fn storage_upload_requests<T>(
+    client: &T,
+    base: &Uri,
+    user_token: &str,
+    reqs: &[UploadRequestRequest],
+) -> Result<Vec<Result<UploadRequestResponse>>>
+where
+    T: Transport + ?Sized,
+{
+    let ids: Vec<&NodeId> = reqs.iter().map(UploadRequestRequest::id).collect();
+    storage_put_batch(
+        client,
+        base,
+        user_token,
+        "UploadRequest",
+        "/document-storage/json/2/upload/request",
+        serde_json::to_string(reqs)?,
+        &ids,
+    )
+    .await
+}
+
+/// Put a blob to the URL given in an [`UploadRequestResponse`]
+///
+/// An expired URL is not even tried.  Returns the number of bytes uploaded
+pub async fn storage_put_blob<T>(
+    client: &T,
+    blob_url_put: &BlobUrl,
+    zipfile: Vec<u8>,
+) -> Result<usize>
+where
+    T: Transport + ?Sized,
+{
+    if blob_url_put.is_expired() {
+        return Err(Error::BlobUrlExpired {
+            api: "UploadRequestBlobPut",
+        });
+    }
+    let lenzip = zipfile.len();
     let request = Request::builder()
         .method("PUT")
-        .header("Authorization", format!("Bearer {}", user_token))
-        .uri(catenate_url_path(
-            base,
-            "/document-storage/json/2/upload/update-status",
-        )?)
-        .body(Body::from(serde_json::to_string(&[&req])?))?;
+        .uri(blob_url_put.url())
+        .body(Body::from(zipfile))?;
     let response = client.request(request).await?;
+    check_blob_status("UploadRequestBlobPut", blob_url_put, response).await?;
+    Ok(lenzip)
+}
 
-    if !response.status().is_success() {
-        return Err(format!("API:UpdateStatus:{:?}", response).into());
-    }
+/// How much of a blob to read and send at once
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
 
-    let ret = hoover_body_to_vec(response.into_body()).await?;
-    let ret: Vec<UpdateStatusResponse> = serde_json::from_slice(&ret)?;
-    if ret.len() != 1 {
-        return Err(format!("API:UpdateStatus:{} responses", ret.len()).into());
+/// Feed exactly `length` bytes from `reader` into a request body
+async fn send_blob<R>(
+    sender: &mut hyper::body::Sender,
+    reader: &mut R,
+    length: u64,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = vec![0; UPLOAD_CHUNK_SIZE];
+    let mut sent = 0;
+    progress(Progress::new(sent, Some(length)));
+    while sent < length {
+        let want = (length - sent).min(UPLOAD_CHUNK_SIZE as u64) as usize;
+        let got = reader.read(&mut buf[..want]).await?;
+        if got == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Blob ended after {} of {} bytes", sent, length),
+            )
+            .into());
+        }
+        sender.send_data(buf[..got].to_vec().into()).await?;
+        sent += got as u64;
+        progress(Progress::new(sent, Some(length)));
     }
-    let ret = &ret[0];
-    if !ret.success() {
-        return Err(format!("API:UpdateStatus:{}", ret.message()).into());
+    Ok(())
+}
+
+/// Put a blob to the URL given in an [`UploadRequestResponse`], streaming
+/// it from `reader`
+///
+/// Exactly `length` bytes are read from `reader`, and `progress` is called
+/// as each chunk is sent.  An expired URL is not even tried.  Returns the
+/// number of bytes uploaded
+pub async fn storage_put_blob_from<T, R>(
+    client: &T,
+    blob_url_put: &BlobUrl,
+    mut reader: R,
+    length: u64,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<u64>
+where
+    T: Transport + ?Sized,
+    R: AsyncRead + Unpin,
+{
+    if blob_url_put.is_expired() {
+        return Err(Error::BlobUrlExpired {
+            api: "UploadRequestBlobPut",
+        });
     }
+    let (mut sender, body) = Body::channel();
+    let request = Request::builder()
+        .method("PUT")
+        .header("Content-Length", length)
+        .uri(blob_url_put.url())
+        .body(body)?;
+    let send = async {
+        let ret = send_blob(&mut sender, &mut reader, length, progress).await;
+        if ret.is_err() {
+            // Make sure the server doesn't see a short body as complete
+            sender.abort();
+        }
+        ret
+    };
+    let (response, sent) = future::join(client.request(request), send).await;
 
-    Ok(())
+    // If we failed to read the blob then that's the root cause of any
+    // failure of the request
+    if let Err(Error::Io(e)) = sent {
+        return Err(Error::Io(e));
+    }
+    check_blob_status("UploadRequestBlobPut", blob_url_put, response?).await?;
+    sent?;
+    Ok(length)
 }
 
-pub async fn storage_upload_doc<C>(
-    client: &Client<C, Body>,
+#[allow(clippy::too_many_arguments)]
+pub async fn storage_upload_doc<T>(
+    client: &T,
     base: &Uri,
     user_token: &str,
-    id: &str,
+    id: &NodeId,
     version: usize,
-    parent: &str,
-    node_type: NodeType,
+    parent: &ParentRef,
+    node_type: &NodeType,
     bookmarked: bool,
     current_page: usize,
     name: &str,
-    modified_client: &str,
+    modified_client: &Timestamp,
     zipfile: Vec<u8>,
-) -> GenericResult<usize>
+) -> Result<usize>
 where
-    C: Connect + Sync + 'static,
+    T: Transport + ?Sized,
 {
-    let req = UploadRequestRequest::new(id, parent, node_type, version);
-    let request = Request::builder()
-        .method("PUT")
-        .header("Authorization", format!("Bearer {}", user_token))
-        .uri(catenate_url_path(
-            base,
-            "/document-storage/json/2/upload/request",
-        )?)
-        .body(Body::from(serde_json::to_string(&[&req])?))?;
-    let response = client.request(request).await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API:UploadRequest:{:?}", response).into());
-    }
-
-    let ret = hoover_body_to_vec(response.into_body()).await?;
-    let ret: Vec<UploadRequestResponse> = serde_json::from_slice(&ret)?;
-    if ret.len() != 1 {
-        return Err(format!("API:UpdateStatus:{} responses", ret.len()).into());
-    }
-    let ret = &ret[0];
-    if !ret.success() {
-        return Err(format!("API:UploadRequest:{}", ret.message()).into());
-    }
+    let req = UploadRequestRequest::new(id.clone(), parent.clone(), node_type.clone(), version);
+    let ret = storage_upload_requests(client, base, user_token, &[req]).await?;
+    let ret = single_result(ret)?;
 
     // We succeeded in requesting the upload, so put the blob
-    let lenzip = zipfile.len();
-    let request = Request::builder()
-        .method("PUT")
-        .uri(ret.blob_url_put())
-        .body(Body::from(zipfile))?;
-    let response = client.request(request).await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API:UploadRequestBlobPut:{:?}", response).into());
-    }
+    let lenzip = storage_put_blob(client, &BlobUrl::for_upload(&ret)?, zipfile).await?;
 
     // Now complete the update
 
@@ -334,3 +831,199 @@ where
 
     Ok(lenzip)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::Client;
+    use tokio::net::TcpListener;
+
+    const OK_EMPTY: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+
+    /// Accept one request, returning its head and body after sending `reply`
+    async fn serve_once(mut listener: TcpListener, reply: &[u8]) -> (String, Vec<u8>) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8];
+            if stream.read(&mut byte).await.unwrap() == 0 {
+                break;
+            }
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).unwrap();
+        let length: usize = head
+            .lines()
+            .find(|line| line.starts_with("content-length: "))
+            .map_or(0, |line| line["content-length: ".len()..].parse().unwrap());
+        let mut body = vec![0; length];
+        let mut got = 0;
+        while got < length {
+            match stream.read(&mut body[got..]).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => got += n,
+            }
+        }
+        body.truncate(got);
+        let _ = stream.write_all(reply).await;
+        (head, body)
+    }
+
+    #[tokio::test]
+    async fn blob_is_streamed_with_length() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let blob: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+
+        let mut reports = Vec::new();
+        let mut progress = |p| reports.push(p);
+        let client = Client::new();
+        let upload =
+            storage_put_blob_from(&client, &url, &blob[..], blob.len() as u64, &mut progress);
+        let ((head, body), sent) = future::join(serve_once(listener, OK_EMPTY), upload).await;
+
+        assert_eq!(sent.unwrap(), 200_000);
+        assert!(head.starts_with("PUT /blob HTTP/1.1\r\n"));
+        assert!(head.contains("content-length: 200000\r\n"));
+        assert_eq!(body, blob);
+        assert_eq!(reports.first(), Some(&Progress::new(0, Some(200_000))));
+        assert_eq!(reports.last(), Some(&Progress::new(200_000, Some(200_000))));
+    }
+
+    #[tokio::test]
+    async fn short_blob_is_io_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let blob = vec![0u8; 1000];
+
+        let client = Client::new();
+        let mut progress = |_| {};
+        let upload = storage_put_blob_from(&client, &url, &blob[..], 2000, &mut progress);
+        let (_, sent) = future::join(serve_once(listener, OK_EMPTY), upload).await;
+
+        match sent {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn blob_is_fetched_with_progress() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let reply = b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world";
+
+        let mut output = Vec::new();
+        let mut reports = Vec::new();
+        let mut progress = |p| reports.push(p);
+        let client = Client::new();
+        let fetch = storage_get_blob_to(&client, &url, &mut output, &mut progress);
+        let ((head, _), fetched) = future::join(serve_once(listener, reply), fetch).await;
+
+        assert_eq!(fetched.unwrap(), 11);
+        assert!(head.starts_with("GET /blob HTTP/1.1\r\n"));
+        assert_eq!(output, b"hello world");
+        assert_eq!(reports.first(), Some(&Progress::new(0, Some(11))));
+        assert_eq!(reports.last(), Some(&Progress::new(11, Some(11))));
+    }
+
+    #[tokio::test]
+    async fn truncated_blob_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let reply = b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nhello world";
+
+        let mut output = Vec::new();
+        let mut progress = |_| {};
+        let client = Client::new();
+        let fetch = storage_get_blob_to(&client, &url, &mut output, &mut progress);
+        let (_, fetched) = future::join(serve_once(listener, reply), fetch).await;
+
+        assert!(fetched.is_err());
+    }
+
+    fn partial_file(name: &str, content: &[u8]) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("remsync-{}-{}", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn partial_blob_is_resumed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let reply = b"HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\n\
+                      Content-Range: bytes 6-10/11\r\n\r\nworld";
+        let path = partial_file("resumed", b"hello ");
+
+        let mut reports = Vec::new();
+        let mut progress = |p| reports.push(p);
+        let client = Client::new();
+        let fetch = storage_resume_blob_file(&client, &url, &path, &mut progress);
+        let ((head, _), fetched) = future::join(serve_once(listener, reply), fetch).await;
+
+        assert_eq!(fetched.unwrap(), (11, Some(11)));
+        assert!(head.contains("range: bytes=6-\r\n"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+        assert_eq!(reports.first(), Some(&Progress::new(6, Some(11))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ignored_range_restarts_blob() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let reply = b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world";
+        let path = partial_file("restarted", b"stale blob content");
+
+        let mut progress = |_| {};
+        let client = Client::new();
+        let fetch = storage_resume_blob_file(&client, &url, &path, &mut progress);
+        let (_, fetched) = future::join(serve_once(listener, reply), fetch).await;
+
+        assert_eq!(fetched.unwrap(), (11, Some(11)));
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn mismatched_partial_blob_is_discarded() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = BlobUrl::new(
+            format!("http://{}/blob", listener.local_addr().unwrap()),
+            None,
+        );
+        let reply = b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\n\
+                      Content-Range: bytes */11\r\n\r\n";
+        let path = partial_file("mismatched", b"far too much stale blob content");
+
+        let mut progress = |_| {};
+        let client = Client::new();
+        let fetch = storage_resume_blob_file(&client, &url, &path, &mut progress);
+        let (_, fetched) = future::join(serve_once(listener, reply), fetch).await;
+
+        // The same URL isn't used again, the caller must fetch a fresh one
+        assert!(matches!(fetched, Err(Error::BlobRestart { .. })));
+        assert!(std::fs::read(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
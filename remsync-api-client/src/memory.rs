@@ -0,0 +1,164 @@
+//! An in-memory transport for tests
+//!
+//! A [`MemoryTransport`] never touches the network.  Instead it is given a
+//! set of routes, each a handler for requests with a particular method,
+//! host and path, and it records every request it sees.  This makes it
+//! possible to script fake authentication, discovery and storage servers
+//! and then drive a [`StorageSession`](crate::StorageSession) against them.
+//!
+//! This module is only available with the `in-memory` feature.
+
+use std::sync::{Arc, Mutex};
+
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode, Uri};
+
+use crate::util::hoover_body_to_vec;
+use crate::{Transport, TransportFuture};
+
+/// A request as seen by a [`MemoryTransport`]
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// The request method
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The full URI requested
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The request headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The value of the named header, if present and valid text
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// The request body
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+type Handler = Arc<dyn Fn(&RecordedRequest) -> Response<Vec<u8>> + Send + Sync>;
+
+struct Route {
+    method: Method,
+    host: String,
+    path: String,
+    handler: Handler,
+}
+
+/// A transport which answers requests from in-memory handlers
+///
+/// Requests which match no route get a `404 Not Found`.  If several routes
+/// match, the most recently added one wins, so a test can override part of
+/// a standard set of routes.
+#[derive(Default)]
+pub struct MemoryTransport {
+    routes: Mutex<Vec<Route>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl MemoryTransport {
+    /// Create a transport with no routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests for `method` on `host` and `path` with `handler`
+    ///
+    /// The path is matched without any query string.  The handler may be
+    /// called any number of times, and can keep state of its own to script
+    /// a sequence of responses.
+    pub fn route<F>(&self, method: Method, host: &str, path: &str, handler: F)
+    where
+        F: Fn(&RecordedRequest) -> Response<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.routes.lock().unwrap().push(Route {
+            method,
+            host: host.to_owned(),
+            path: path.to_owned(),
+            handler: Arc::new(handler),
+        });
+    }
+
+    /// Every request this transport has seen, in order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// How many requests have been made for `path`, on any host
+    pub fn count(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|req| req.uri.path() == path)
+            .count()
+    }
+
+    fn handler_for(&self, request: &RecordedRequest) -> Option<Handler> {
+        let host = request.uri.host().unwrap_or("");
+        self.routes
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|route| {
+                route.method == request.method
+                    && route.host == host
+                    && route.path == request.uri.path()
+            })
+            .map(|route| route.handler.clone())
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn request(&self, request: Request<Body>) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let request = RecordedRequest {
+                method: parts.method,
+                uri: parts.uri,
+                headers: parts.headers,
+                body: hoover_body_to_vec(body).await?,
+            };
+            self.requests.lock().unwrap().push(request.clone());
+            let response = match self.handler_for(&request) {
+                Some(handler) => handler(&request),
+                None => response(StatusCode::NOT_FOUND, ""),
+            };
+            Ok(response.map(Body::from))
+        })
+    }
+}
+
+/// Build a response with the given status and body
+pub fn response<B: Into<Vec<u8>>>(status: StatusCode, body: B) -> Response<Vec<u8>> {
+    let body = body.into();
+    Response::builder()
+        .status(status)
+        .header("Content-Length", body.len())
+        .body(body)
+        .expect("Unable to build response")
+}
+
+/// Build a `200 OK` response with the given value as its JSON body
+pub fn json<T: serde::Serialize>(value: &T) -> Response<Vec<u8>> {
+    response(
+        StatusCode::OK,
+        serde_json::to_vec(value).expect("Unable to serialise response"),
+    )
+}
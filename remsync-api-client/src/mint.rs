@@ -0,0 +1,399 @@
+//! Issuing the JWTs used as device and user tokens
+//!
+//! A self-hosted deployment has to hand out tokens of its own, and they have
+//! to look enough like the ones from reMarkable's auth service (which is
+//! built on auth0, see protocol.md) that devices are happy with them.  A
+//! [`TokenIssuer`] signs [`DeviceToken`] and [`UserToken`] claims with one
+//! of its keys, and can publish the public halves of those keys as a JWKS
+//! document for a [`TokenVerifier`](crate::jwt::TokenVerifier) to check them
+//! against.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use jsonwebtoken::{Algorithm, Header};
+use remsync_api_types::*;
+use serde::Serialize;
+
+use crate::jwt::{Jwk, Jwks};
+use crate::{Error, Result};
+
+/// The issuer named in reMarkable's own tokens
+pub const DEFAULT_ISSUER: &str = "rM WebApp";
+
+/// How long user tokens last by default, as reMarkable's do
+pub const DEFAULT_USER_TOKEN_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The subject of every device token
+const DEVICE_TOKEN_SUBJECT: &str = "rM Device Token";
+
+/// A key which tokens can be signed with
+#[derive(Clone)]
+pub struct SigningKey {
+    kid: Option<String>,
+    alg: Algorithm,
+    material: Vec<u8>,
+    public: Option<Jwk>,
+}
+
+impl SigningKey {
+    /// Sign with the shared secret `secret`, using `HS256`
+    ///
+    /// The secret is not published in a JWKS document, so verifiers must
+    /// be given it directly.
+    pub fn hmac(secret: &[u8]) -> Self {
+        Self {
+            kid: None,
+            alg: Algorithm::HS256,
+            material: secret.to_vec(),
+            public: None,
+        }
+    }
+
+    /// Sign with the RSA key `der`, a DER encoded PKCS#1 `RSAPrivateKey`,
+    /// using `RS256`
+    pub fn rsa(der: &[u8]) -> Result<Self> {
+        let (n, e) = rsa_public_numbers(der)
+            .ok_or_else(|| Error::Config("Unusable RSA private key".to_owned()))?;
+        Ok(Self {
+            kid: None,
+            alg: Algorithm::RS256,
+            material: der.to_vec(),
+            public: Some(Jwk {
+                kty: "RSA".to_owned(),
+                n: Some(base64url(n)),
+                e: Some(base64url(e)),
+                ..Jwk::default()
+            }),
+        })
+    }
+
+    /// Sign with the P-256 key `pkcs8`, a DER encoded PKCS#8 document
+    /// which includes the public key, using `ES256`
+    pub fn ec(pkcs8: &[u8]) -> Result<Self> {
+        let (x, y) = ec_public_point(pkcs8)
+            .ok_or_else(|| Error::Config("Unusable ECDSA private key".to_owned()))?;
+        Ok(Self {
+            kid: None,
+            alg: Algorithm::ES256,
+            material: pkcs8.to_vec(),
+            public: Some(Jwk {
+                kty: "EC".to_owned(),
+                crv: Some("P-256".to_owned()),
+                x: Some(base64url(x)),
+                y: Some(base64url(y)),
+                ..Jwk::default()
+            }),
+        })
+    }
+
+    /// Name the key `kid`, both in the tokens it signs and in the JWKS
+    pub fn with_kid(mut self, kid: &str) -> Self {
+        self.kid = Some(kid.to_owned());
+        self
+    }
+
+    /// The algorithm tokens are signed with
+    pub fn algorithm(&self) -> Algorithm {
+        self.alg
+    }
+
+    /// The public half of the key as a JWK, unless it is a shared secret
+    pub fn jwk(&self) -> Option<Jwk> {
+        self.public.as_ref().map(|public| Jwk {
+            kid: self.kid.clone(),
+            alg: Some(format!("{:?}", self.alg)),
+            key_use: Some("sig".to_owned()),
+            ..public.clone()
+        })
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never show the private key
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("alg", &self.alg)
+            .finish()
+    }
+}
+
+/// Issues signed device and user tokens
+///
+/// Tokens name [`DEFAULT_ISSUER`] as their issuer and user tokens last for
+/// [`DEFAULT_USER_TOKEN_LIFETIME`] unless the issuer is told otherwise.
+/// Each token gets a random `jti` in the style of reMarkable's own.
+#[derive(Clone)]
+pub struct TokenIssuer {
+    key: SigningKey,
+    retired: Vec<SigningKey>,
+    issuer: String,
+    lifetime: Duration,
+    jti: Arc<dyn Fn() -> String + Send + Sync>,
+}
+
+impl TokenIssuer {
+    /// An issuer which signs tokens with `key`
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            key,
+            retired: Vec::new(),
+            issuer: DEFAULT_ISSUER.to_owned(),
+            lifetime: DEFAULT_USER_TOKEN_LIFETIME,
+            jti: Arc::new(random_jti),
+        }
+    }
+
+    /// Name `issuer` as the issuer of tokens
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = issuer.to_owned();
+        self
+    }
+
+    /// Issue user tokens which last for `lifetime`
+    pub fn with_user_token_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Use `jti` to generate the ID of each token
+    pub fn with_jti_generator<F>(mut self, jti: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.jti = Arc::new(jti);
+        self
+    }
+
+    /// Keep publishing `key` in the JWKS, without signing with it
+    ///
+    /// When rotating keys, this lets tokens signed with the old key be
+    /// verified until they have all expired.
+    pub fn with_retired_key(mut self, key: SigningKey) -> Self {
+        self.retired.push(key);
+        self
+    }
+
+    /// The public keys which tokens from this issuer can be checked with
+    pub fn jwks(&self) -> Jwks {
+        Jwks {
+            keys: std::iter::once(&self.key)
+                .chain(&self.retired)
+                .filter_map(SigningKey::jwk)
+                .collect(),
+        }
+    }
+
+    /// Issue a device token, valid from now, for a newly registered device
+    pub fn issue_device_token(
+        &self,
+        auth0_user_id: &str,
+        device_desc: &str,
+        device_id: &str,
+    ) -> Result<String> {
+        let now = now_epoch_secs();
+        let claims = DeviceToken::new(
+            auth0_user_id,
+            device_desc,
+            device_id,
+            now,
+            &self.issuer,
+            &(self.jti)(),
+            now,
+            DEVICE_TOKEN_SUBJECT,
+        );
+        self.sign(&claims)
+    }
+
+    /// Issue a user token for `profile`, on the device `device`
+    pub fn issue_user_token(&self, profile: Auth0Profile, device: &DeviceToken) -> Result<String> {
+        let claims = UserToken::new(
+            profile,
+            device.device_desc(),
+            device.device_id(),
+            now_epoch_secs(),
+            self.lifetime,
+            &self.issuer,
+            &(self.jti)(),
+        );
+        self.sign(&claims)
+    }
+
+    /// Sign any claims with our key
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let mut header = Header::new(self.key.alg);
+        header.kid = self.key.kid.clone();
+        Ok(jsonwebtoken::encode(&header, claims, &self.key.material)?)
+    }
+}
+
+/// A random token ID, as eight bytes of base64 like reMarkable's
+fn random_jti() -> String {
+    let bytes: [u8; 8] = rand::random();
+    base64::encode(&bytes)
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Split the DER element at the start of `input` into its tag, its
+/// contents, and whatever follows it
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        usize::from(first)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > std::mem::size_of::<usize>() || input.len() < count {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(count);
+        input = rest;
+        bytes.iter().fold(0, |len, b| len << 8 | usize::from(*b))
+    };
+    if input.len() < len {
+        return None;
+    }
+    let (contents, rest) = input.split_at(len);
+    Some((tag, contents, rest))
+}
+
+/// The contents of the DER element with tag `want` at the start of
+/// `input`, and whatever follows it
+fn der_expect(input: &[u8], want: u8) -> Option<(&[u8], &[u8])> {
+    match der_element(input)? {
+        (tag, contents, rest) if tag == want => Some((contents, rest)),
+        _ => None,
+    }
+}
+
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_SEQUENCE: u8 = 0x30;
+/// The `[1]` explicit tag holding the public key in an `ECPrivateKey`
+const DER_EC_PUBLIC_KEY: u8 = 0xa1;
+
+/// Drop the sign padding from an unsigned DER integer
+fn unsigned(value: &[u8]) -> &[u8] {
+    match value.split_first() {
+        Some((0, rest)) if !rest.is_empty() => rest,
+        _ => value,
+    }
+}
+
+/// The modulus and public exponent of a PKCS#1 `RSAPrivateKey`
+fn rsa_public_numbers(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (key, _) = der_expect(der, DER_SEQUENCE)?;
+    let (_version, key) = der_expect(key, DER_INTEGER)?;
+    let (n, key) = der_expect(key, DER_INTEGER)?;
+    let (e, _) = der_expect(key, DER_INTEGER)?;
+    Some((unsigned(n), unsigned(e)))
+}
+
+/// The x and y coordinates of the public key in a PKCS#8 wrapped P-256
+/// `ECPrivateKey`
+fn ec_public_point(pkcs8: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (info, _) = der_expect(pkcs8, DER_SEQUENCE)?;
+    let (_version, info) = der_expect(info, DER_INTEGER)?;
+    let (_algorithm, info) = der_expect(info, DER_SEQUENCE)?;
+    let (key, _) = der_expect(info, DER_OCTET_STRING)?;
+    let (key, _) = der_expect(key, DER_SEQUENCE)?;
+    let (_version, key) = der_expect(key, DER_INTEGER)?;
+    let (_private, mut key) = der_expect(key, DER_OCTET_STRING)?;
+    while !key.is_empty() {
+        let (tag, contents, rest) = der_element(key)?;
+        if tag == DER_EC_PUBLIC_KEY {
+            let (bits, _) = der_expect(contents, DER_BIT_STRING)?;
+            return match bits {
+                [0, 0x04, point @ ..] if point.len() == 64 => Some(point.split_at(32)),
+                _ => None,
+            };
+        }
+        key = rest;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jwt::TokenVerifier;
+    use crate::testutil::user_profile;
+
+    const JWKS: &[u8] = include_bytes!("../testdata/jwks.json");
+    const RSA_KEY: &[u8] = include_bytes!("../testdata/rsa-private.der");
+    const EC_KEY: &[u8] = include_bytes!("../testdata/ec-private.p8");
+
+    #[test]
+    fn jwks_matches_private_keys() {
+        let rsa = SigningKey::rsa(RSA_KEY).unwrap().with_kid("rsa-test");
+        let ec = SigningKey::ec(EC_KEY).unwrap().with_kid("ec-test");
+        let issuer = TokenIssuer::new(rsa).with_retired_key(ec);
+        let expected: Jwks = serde_json::from_slice(JWKS).unwrap();
+        assert_eq!(issuer.jwks(), expected);
+        assert!(SigningKey::rsa(EC_KEY).is_err());
+        assert!(SigningKey::ec(RSA_KEY).is_err());
+        assert!(SigningKey::hmac(b"secret").jwk().is_none());
+    }
+
+    #[test]
+    fn keys_are_not_shown() {
+        let key = SigningKey::hmac(b"secret").with_kid("hmac-test");
+        assert_eq!(
+            format!("{:?}", key),
+            r#"SigningKey { kid: Some("hmac-test"), alg: HS256 }"#
+        );
+    }
+
+    #[test]
+    fn issued_tokens_verify() {
+        for key in &[
+            SigningKey::hmac(b"secret"),
+            SigningKey::rsa(RSA_KEY).unwrap().with_kid("rsa-test"),
+            SigningKey::ec(EC_KEY).unwrap().with_kid("ec-test"),
+        ] {
+            let issuer = TokenIssuer::new(key.clone())
+                .with_issuer("remsync test")
+                .with_user_token_lifetime(Duration::from_secs(3600))
+                .with_jti_generator(|| "some-token-id".to_owned());
+            let verifier = TokenVerifier::new()
+                .with_hmac_secret(b"secret")
+                .with_jwks(&issuer.jwks())
+                .unwrap();
+
+            let device = issuer
+                .issue_device_token("auth0|some-user", "desktop-linux", "some-device-id")
+                .unwrap();
+            let device = verifier.verify_device_token(&device).unwrap();
+            assert_eq!(device.issuer(), "remsync test");
+            assert_eq!(device.token_identity(), "some-token-id");
+            assert_eq!(device.subject(), "rM Device Token");
+
+            let user = issuer.issue_user_token(user_profile(), &device).unwrap();
+            let user = verifier.verify_user_token(&user).unwrap();
+            assert_eq!(user.device_id(), "some-device-id");
+            assert_eq!(user.expires_at(), user.issued_at() + 3600);
+            assert_eq!(user.auth0_profile().user_id(), user_profile().user_id());
+        }
+    }
+
+    #[test]
+    fn token_ids_are_random() {
+        let issuer = TokenIssuer::new(SigningKey::hmac(b"secret"));
+        let verifier = TokenVerifier::new().with_hmac_secret(b"secret");
+        let ids: Vec<String> = (0..2)
+            .map(|_| {
+                let token = issuer.issue_device_token("user", "desc", "id").unwrap();
+                let claims = verifier.verify_device_token(&token).unwrap();
+                claims.token_identity().to_owned()
+            })
+            .collect();
+        assert_eq!(ids[0].len(), 12);
+        assert_ne!(ids[0], ids[1]);
+    }
+}
@@ -0,0 +1,301 @@
+//! The notification service websocket
+//!
+//! The notification service pushes a [`NotificationEvent`] over a websocket
+//! whenever a node changes, so that clients can react to changes made on
+//! other devices without polling.  A [`NotificationSocket`] is a single
+//! connection to that service, as made by
+//! [`ll::notifications_connect`](crate::ll::notifications_connect).  A
+//! [`NotificationStream`] wraps a series of such connections, reconnecting
+//! with a [`Backoff`] whenever one fails.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Instant;
+
+use hyper::upgrade::Upgraded;
+use remsync_api_types::{from_received_slice, NotificationEvent};
+use tokio::codec::Framed;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::prelude::*;
+use tokio::stream;
+use tokio::timer::delay;
+
+use crate::ws::{Message, WebSocketCodec, CLOSE_NORMAL};
+use crate::{Backoff, Result};
+
+/// A stream of notification events which survives disconnection
+///
+/// Connection failures are yielded as errors and then the stream waits
+/// according to its [`Backoff`] before reconnecting.  The stream never
+/// ends, simply drop it when you are no longer interested.
+pub type NotificationStream<'a> = Pin<Box<dyn Stream<Item = Result<NotificationEvent>> + 'a>>;
+
+/// A single websocket connection to the notification service
+pub struct NotificationSocket<T = Upgraded> {
+    framed: Framed<T, WebSocketCodec>,
+    closed: bool,
+    lenient_ids: bool,
+}
+
+impl<T> NotificationSocket<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    pub(crate) fn new(framed: Framed<T, WebSocketCodec>) -> Self {
+        Self {
+            framed,
+            closed: false,
+            lenient_ids: false,
+        }
+    }
+
+    /// Accept node IDs which are not UUIDs in events, as
+    /// [`NodeId::parse_received`] does when asked to be lenient
+    pub fn with_lenient_ids(mut self, lenient: bool) -> Self {
+        self.lenient_ids = lenient;
+        self
+    }
+
+    /// Wait for the next notification event
+    ///
+    /// Pings from the server are answered while waiting.  Returns `None`
+    /// once the connection has closed, after which the socket is of no
+    /// further use.  A message which cannot be decoded is reported as an
+    /// error but leaves the connection open.
+    pub async fn next_event(&mut self) -> Option<Result<NotificationEvent>> {
+        while !self.closed {
+            let message = match self.framed.next().await {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    self.closed = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.closed = true;
+                    return None;
+                }
+            };
+            match message {
+                Message::Text(text) => {
+                    return Some(self.decode(text.as_bytes()));
+                }
+                Message::Binary(data) => {
+                    return Some(self.decode(&data));
+                }
+                Message::Ping(data) => {
+                    if let Err(e) = self.framed.send(Message::Pong(data)).await {
+                        self.closed = true;
+                        return Some(Err(e));
+                    }
+                }
+                Message::Pong(_) => {}
+                Message::Close(mut data) => {
+                    // Echo the status code back, as the protocol requires,
+                    // but there's nothing to be done if that fails
+                    data.truncate(2);
+                    let _ = self.framed.send(Message::Close(data)).await;
+                    self.closed = true;
+                }
+            }
+        }
+        None
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<NotificationEvent> {
+        Ok(from_received_slice(data, self.lenient_ids)?)
+    }
+
+    /// Close the connection cleanly
+    pub async fn close(mut self) -> Result<()> {
+        if !self.closed {
+            self.closed = true;
+            self.framed.send(Message::close(CLOSE_NORMAL)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Build a [`NotificationStream`] from a function which makes connections
+///
+/// Consecutive failures (including connections which close without
+/// delivering any events) increase the delay before the next attempt,
+/// and any received event resets it.
+pub(crate) fn reconnecting<'a, F, Fut, T>(connect: F, backoff: Backoff) -> NotificationStream<'a>
+where
+    F: FnMut() -> Fut + 'a,
+    Fut: Future<Output = Result<NotificationSocket<T>>> + 'a,
+    T: AsyncRead + AsyncWrite + Unpin + 'a,
+{
+    let state = (connect, None, 0);
+    Box::pin(stream::unfold(
+        state,
+        move |(mut connect, socket, mut failures)| async move {
+            let mut socket: Option<NotificationSocket<T>> = socket;
+            loop {
+                let mut current = match socket.take() {
+                    Some(current) => current,
+                    None => {
+                        if failures > 0 {
+                            delay(Instant::now() + backoff.delay(failures)).await;
+                        }
+                        match connect().await {
+                            Ok(current) => current,
+                            Err(e) => return Some((Err(e), (connect, None, failures + 1))),
+                        }
+                    }
+                };
+                match current.next_event().await {
+                    Some(Ok(event)) => return Some((Ok(event), (connect, Some(current), 0))),
+                    Some(Err(e)) => {
+                        if current.closed {
+                            failures += 1;
+                            return Some((Err(e), (connect, None, failures)));
+                        }
+                        return Some((Err(e), (connect, Some(current), failures)));
+                    }
+                    None => failures += 1,
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil::node_id;
+    use crate::ws::{accept_key, Role};
+    use crate::Error;
+    use futures::future;
+    use hyper::Client;
+    use remsync_api_types::*;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn event(id: &NodeId) -> NotificationEvent {
+        let attrs = NotificationMessageAttributes::new(
+            "some-user-id",
+            false,
+            NotificationEventType::DocAdded,
+            id.clone(),
+            ParentRef::Root,
+            "some-device-desc",
+            "some-device-id",
+            NodeType::DocumentType,
+            1,
+            "My Shiny Node",
+        );
+        let msg = NotificationMessage::new(
+            attrs,
+            "some-message-id",
+            "2019-08-31T15:36:45.576Z".parse().unwrap(),
+        );
+        NotificationEvent::new(msg, "some-subscription-name")
+    }
+
+    /// Accept one connection and complete the websocket handshake
+    async fn accept(listener: &mut TcpListener) -> Framed<TcpStream, WebSocketCodec> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8];
+            assert_eq!(stream.read(&mut byte).await.unwrap(), 1);
+            request.push(byte[0]);
+        }
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("GET /notifications/ws/json/1 HTTP/1.1\r\n"));
+        assert!(request.contains("authorization: Bearer some-user-token\r\n"));
+        let key = request
+            .lines()
+            .find(|line| line.starts_with("sec-websocket-key: "))
+            .unwrap()["sec-websocket-key: ".len()..]
+            .to_owned();
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Connection: Upgrade\r\n\
+             Upgrade: websocket\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        Framed::new(stream, WebSocketCodec::new(Role::Server))
+    }
+
+    fn send_event(id: &NodeId) -> Message {
+        Message::Text(serde_json::to_string(&event(id)).unwrap())
+    }
+
+    #[tokio::test]
+    async fn events_are_received() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base: hyper::Uri = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let server = async move {
+            let mut ws = accept(&mut listener).await;
+            ws.send(send_event(&node_id(1))).await.unwrap();
+            ws.send(Message::Ping(b"hello".to_vec())).await.unwrap();
+            ws.send(Message::Text("not json".to_owned())).await.unwrap();
+            ws.send(send_event(&node_id(2))).await.unwrap();
+            assert_eq!(
+                ws.next().await.unwrap().unwrap(),
+                Message::Pong(b"hello".to_vec())
+            );
+            ws.send(Message::close(CLOSE_NORMAL)).await.unwrap();
+            assert_eq!(
+                ws.next().await.unwrap().unwrap(),
+                Message::close(CLOSE_NORMAL)
+            );
+        };
+
+        let client = async move {
+            let client = Client::new();
+            let mut socket = crate::ll::notifications_connect(&client, &base, "some-user-token")
+                .await
+                .unwrap();
+            let first = socket.next_event().await.unwrap().unwrap();
+            assert_eq!(*first.message().attributes().id(), node_id(1));
+            match socket.next_event().await {
+                Some(Err(Error::Json(_))) => {}
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            let second = socket.next_event().await.unwrap().unwrap();
+            assert_eq!(*second.message().attributes().id(), node_id(2));
+            assert!(socket.next_event().await.is_none());
+        };
+
+        future::join(server, client).await;
+    }
+
+    #[tokio::test]
+    async fn stream_reconnects() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base: hyper::Uri = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let server = async move {
+            for n in 1..=2 {
+                let mut ws = accept(&mut listener).await;
+                ws.send(send_event(&node_id(n))).await.unwrap();
+                // Drop the connection without a close handshake
+            }
+        };
+
+        let client = async move {
+            let client = Client::new();
+            let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10));
+            let mut stream = reconnecting(
+                || crate::ll::notifications_connect(&client, &base, "some-user-token"),
+                backoff,
+            );
+            for n in 1..=2 {
+                let event = stream.next().await.unwrap().unwrap();
+                assert_eq!(*event.message().attributes().id(), node_id(n));
+            }
+        };
+
+        future::join(server, client).await;
+    }
+}
@@ -0,0 +1,207 @@
+//! Named deployments of the cloud services
+//!
+//! The official firmware knows of several deployments of the cloud
+//! services (see `hacking-binary.md`), each with its own authentication
+//! and service manager hosts and its own `environment` for discovery.  A
+//! [`Profile`] bundles those together so a session can be pointed at any
+//! of them by name, including a self-hosted deployment which mirrors the
+//! official host names under a domain of its own.
+
+use hyper::Uri;
+
+use crate::{ll, Error, Result};
+
+/// The domain a self-hosted deployment lives under unless told otherwise
+pub const SELF_HOSTED_DOMAIN: &str = "remsync.net";
+
+/// Everything needed to find the services of one deployment
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    name: String,
+    auth_base: Uri,
+    discovery_base: Uri,
+    environment: String,
+    storage_api_version: u32,
+    notifications_api_version: u32,
+}
+
+impl Profile {
+    fn new(name: &str, auth_base: &str, discovery_base: &str, environment: &str) -> Result<Self> {
+        Ok(Self {
+            name: name.to_owned(),
+            auth_base: auth_base.parse()?,
+            discovery_base: discovery_base.parse()?,
+            environment: environment.to_owned(),
+            storage_api_version: ll::STORAGE_API_VERSION,
+            notifications_api_version: ll::NOTIFICATIONS_API_VERSION,
+        })
+    }
+
+    /// The deployment used by the official clients
+    pub fn production() -> Self {
+        Self::new(
+            "production",
+            "https://my.remarkable.com/",
+            "https://service-manager-production-dot-remarkable-production.appspot.com/",
+            "production",
+        )
+        .expect("Production URIs are valid")
+    }
+
+    /// The staging deployment
+    pub fn staging() -> Self {
+        Self::new(
+            "staging",
+            "https://testing.my.remarkable.com/",
+            "https://service-manager-staging-dot-remarkable-development.appspot.com/",
+            "staging",
+        )
+        .expect("Staging URIs are valid")
+    }
+
+    /// The development deployment
+    pub fn development() -> Self {
+        Self::new(
+            "development",
+            "https://development-test.my.remarkable.com/",
+            "https://service-manager-development-dot-remarkable-development.appspot.com/",
+            "development",
+        )
+        .expect("Development URIs are valid")
+    }
+
+    /// The QA deployment
+    pub fn qa() -> Self {
+        Self::new(
+            "qa",
+            "https://qa.my.remarkable.com/",
+            "https://service-manager-qa-dot-remarkable-qa.appspot.com/",
+            "qa",
+        )
+        .expect("QA URIs are valid")
+    }
+
+    /// A self-hosted deployment under `domain`
+    ///
+    /// The host names are those of production with `appspot.com` replaced
+    /// by `domain`, which is what a patched tablet will ask for.
+    ///
+    /// ```
+    /// # use remsync_api_client::Profile;
+    /// let profile = Profile::self_hosted("remsync.net").unwrap();
+    /// assert_eq!(
+    ///     profile.discovery_base(),
+    ///     "https://service-manager-production-dot-remarkable-production.remsync.net/"
+    /// );
+    /// ```
+    pub fn self_hosted(domain: &str) -> Result<Self> {
+        Self::new(
+            "self-hosted",
+            &format!(
+                "https://webapp-production-dot-remarkable-production.{}/",
+                domain
+            ),
+            &format!(
+                "https://service-manager-production-dot-remarkable-production.{}/",
+                domain
+            ),
+            "production",
+        )
+    }
+
+    /// Look up a profile by name
+    ///
+    /// The names are `production`, `staging`, `development`, `qa` and
+    /// `self-hosted`.  A self-hosted profile lives under
+    /// [`SELF_HOSTED_DOMAIN`] unless a domain is given after a colon, as in
+    /// `self-hosted:example.org`.
+    ///
+    /// ```
+    /// # use remsync_api_client::Profile;
+    /// assert_eq!(Profile::named("qa").unwrap(), Profile::qa());
+    /// assert!(Profile::named("nowhere").is_err());
+    /// ```
+    pub fn named(name: &str) -> Result<Self> {
+        match name {
+            "production" => Ok(Self::production()),
+            "staging" => Ok(Self::staging()),
+            "development" => Ok(Self::development()),
+            "qa" => Ok(Self::qa()),
+            "self-hosted" => Self::self_hosted(SELF_HOSTED_DOMAIN),
+            _ if name.starts_with("self-hosted:") => {
+                Self::self_hosted(&name["self-hosted:".len()..])
+            }
+            _ => Err(Error::Config(format!(
+                "Unknown environment profile {:?}",
+                name
+            ))),
+        }
+    }
+
+    /// Use a different authentication service
+    pub fn with_auth_base(mut self, auth_base: Uri) -> Self {
+        self.auth_base = auth_base;
+        self
+    }
+
+    /// Use a different service manager
+    pub fn with_discovery_base(mut self, discovery_base: Uri) -> Self {
+        self.discovery_base = discovery_base;
+        self
+    }
+
+    /// The name of the profile
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The base URI of the authentication service
+    pub fn auth_base(&self) -> &Uri {
+        &self.auth_base
+    }
+
+    /// The base URI of the service manager
+    pub fn discovery_base(&self) -> &Uri {
+        &self.discovery_base
+    }
+
+    /// The `environment` to ask the service manager about
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// The `apiVer` used to discover document storage
+    pub fn storage_api_version(&self) -> u32 {
+        self.storage_api_version
+    }
+
+    /// The `apiVer` used to discover notifications
+    pub fn notifications_api_version(&self) -> u32 {
+        self.notifications_api_version
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::production()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profiles_are_named() {
+        for name in &["production", "staging", "development", "qa", "self-hosted"] {
+            assert_eq!(Profile::named(name).unwrap().name(), *name);
+        }
+        let profile = Profile::named("self-hosted:example.org").unwrap();
+        assert_eq!(
+            profile.auth_base(),
+            "https://webapp-production-dot-remarkable-production.example.org/"
+        );
+        assert_eq!(profile.environment(), "production");
+        assert!(Profile::named("self-hosted:not a domain").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Progress reporting for blob transfers
+//!
+//! Blobs can be large, so the functions which transfer them accept a
+//! callback which is given a [`Progress`] as each chunk goes by.
+
+/// How far through a blob transfer we are
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    transferred: u64,
+    total: Option<u64>,
+}
+
+impl Progress {
+    /// Create a progress report
+    ///
+    /// ```
+    /// # use remsync_api_client::Progress;
+    /// let progress = Progress::new(512, Some(1024));
+    /// ```
+    pub fn new(transferred: u64, total: Option<u64>) -> Self {
+        Self { transferred, total }
+    }
+
+    /// The number of bytes transferred so far
+    ///
+    /// ```
+    /// # use remsync_api_client::Progress;
+    /// # let progress = Progress::new(512, Some(1024));
+    /// assert_eq!(progress.transferred(), 512);
+    /// ```
+    pub fn transferred(&self) -> u64 {
+        self.transferred
+    }
+
+    /// The total number of bytes to transfer, if known
+    ///
+    /// ```
+    /// # use remsync_api_client::Progress;
+    /// # let progress = Progress::new(512, Some(1024));
+    /// assert_eq!(progress.total(), Some(1024));
+    /// ```
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// The fraction of the transfer completed, if the total is known
+    ///
+    /// An empty transfer is always complete.
+    ///
+    /// ```
+    /// # use remsync_api_client::Progress;
+    /// # let progress = Progress::new(512, Some(1024));
+    /// assert_eq!(progress.fraction(), Some(0.5));
+    /// assert_eq!(Progress::new(0, Some(0)).fraction(), Some(1.0));
+    /// assert_eq!(Progress::new(0, None).fraction(), None);
+    /// ```
+    pub fn fraction(&self) -> Option<f64> {
+        self.total.map(|total| {
+            if total == 0 {
+                1.0
+            } else {
+                self.transferred as f64 / total as f64
+            }
+        })
+    }
+}
@@ -0,0 +1,496 @@
+//! Recording traffic, and replaying it later
+//!
+//! A [`Recorder`] wraps another transport and writes every exchange it
+//! carries to a JSON-lines file, one [`Exchange`] per line.  Credentials
+//! are redacted on the way: `Authorization` and `Set-Cookie` headers are
+//! dropped, the signatures of any JWTs are removed, and so are one-time
+//! registration codes and the credentials in the query strings of signed
+//! blob URLs.  A [`Replay`] transport serves a recording back again, which
+//! turns a real session into a fixture for tests.
+//!
+//! Since each exchange is written out whole, the recorder holds request and
+//! response bodies in memory, so it's best not used for huge blobs.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use hyper::{Body, HeaderMap, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::util::hoover_body_to_vec;
+use crate::{Error, Result, Transport, TransportFuture};
+
+/// What redacted credentials are replaced with
+const REDACTED: &str = "REDACTED";
+
+/// Words which mark a query parameter as a credential
+const CREDENTIAL_PARAMETERS: &[&str] = &["sig", "credential", "token", "key", "accessid", "auth"];
+
+/// JSON fields which carry a credential, such as the one-time code sent
+/// when registering a device
+const CREDENTIAL_FIELDS: &[&str] = &["code"];
+
+/// Headers which carry credentials, and so are not recorded at all
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "set-cookie"];
+
+/// A message body, kept as text where possible
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Payload {
+    /// A body which is valid UTF-8
+    Text(String),
+    /// Any other body, base64 encoded
+    Base64(String),
+}
+
+impl Payload {
+    fn new(body: Vec<u8>) -> Self {
+        match String::from_utf8(body) {
+            Ok(text) => Payload::Text(text),
+            Err(e) => Payload::Base64(base64::encode(e.as_bytes())),
+        }
+    }
+
+    /// The bytes of the body
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Payload::Text(text) => Ok(text.clone().into_bytes()),
+            Payload::Base64(data) => base64::decode(data)
+                .map_err(|e| Error::protocol("Replay", format!("Bad base64 body: {}", e))),
+        }
+    }
+}
+
+/// A request and the response to it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exchange {
+    method: String,
+    uri: String,
+    request_headers: Vec<(String, String)>,
+    request_body: Payload,
+    status: u16,
+    response_headers: Vec<(String, String)>,
+    response_body: Payload,
+}
+
+impl Exchange {
+    /// The request method
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The request URI, with any signature redacted
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// The request headers, without credentials
+    pub fn request_headers(&self) -> &[(String, String)] {
+        &self.request_headers
+    }
+
+    /// The request body
+    pub fn request_body(&self) -> &Payload {
+        &self.request_body
+    }
+
+    /// The response status code
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The response headers
+    pub fn response_headers(&self) -> &[(String, String)] {
+        &self.response_headers
+    }
+
+    /// The response body
+    pub fn response_body(&self) -> &Payload {
+        &self.response_body
+    }
+}
+
+/// Whether a query parameter with this name carries a credential
+fn is_credential(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    CREDENTIAL_PARAMETERS.iter().any(|word| name.contains(word))
+}
+
+/// Replace the values of any credentials in the query string of `url`
+///
+/// Signed URLs carry their signature, and whatever identifies the signer,
+/// as query parameters.
+fn redact_url(url: &str) -> String {
+    let (base, query) = match url.find('?') {
+        Some(start) => (&url[..start], &url[start + 1..]),
+        None => return url.to_owned(),
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|param| {
+            let name = param.split('=').next().unwrap_or("");
+            if is_credential(name) {
+                format!("{}={}", name, REDACTED)
+            } else {
+                param.to_owned()
+            }
+        })
+        .collect();
+    format!("{}?{}", base, query.join("&"))
+}
+
+fn redacted_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| !CREDENTIAL_HEADERS.contains(&name.as_str()))
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            (name.to_string(), redact_jwts(&value))
+        })
+        .collect()
+}
+
+/// Remove the signature of anything in `text` which looks like a JWT
+///
+/// The header and claims are left alone, so the token still decodes, but
+/// it can no longer be used.
+fn redact_jwts(text: &str) -> String {
+    let is_token_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+    let mut ret = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("eyJ") {
+        ret.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_token_char(c)).unwrap_or(rest.len());
+        let candidate = &rest[..end];
+        let parts: Vec<&str> = candidate.split('.').collect();
+        if parts.len() == 3 && !parts[1].is_empty() {
+            ret.push_str(&format!("{}.{}.{}", parts[0], parts[1], REDACTED));
+        } else {
+            ret.push_str(candidate);
+        }
+        rest = &rest[end..];
+    }
+    ret.push_str(rest);
+    ret
+}
+
+/// Redact the credential fields, and the credentials in any URLs, in a
+/// JSON message
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        serde_json::Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if CREDENTIAL_FIELDS.contains(&name.as_str()) && field.is_string() {
+                    *field = REDACTED.into();
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        serde_json::Value::String(text) if text.starts_with("http") => *text = redact_url(text),
+        _ => {}
+    }
+}
+
+fn redacted_body(body: &[u8]) -> Payload {
+    let body = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(mut value) if value.is_object() || value.is_array() => {
+            redact_json(&mut value);
+            serde_json::to_vec(&value).expect("Unable to reserialise JSON")
+        }
+        _ => body.to_vec(),
+    };
+    match Payload::new(body) {
+        Payload::Text(text) => Payload::Text(redact_jwts(&text)),
+        other => other,
+    }
+}
+
+/// A transport which records everything passing through it
+pub struct Recorder<T> {
+    inner: T,
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl<T> Recorder<T>
+where
+    T: Transport,
+{
+    /// Record the traffic carried by `inner` to `output`
+    pub fn new<W>(inner: T, output: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        Self {
+            inner,
+            output: Mutex::new(Box::new(output)),
+        }
+    }
+
+    /// Record the traffic carried by `inner` to a new file at `path`
+    pub fn create<P: AsRef<Path>>(inner: T, path: P) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::new(inner, BufWriter::new(file)))
+    }
+
+    /// The transport being recorded
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    fn write(&self, exchange: &Exchange) -> Result<()> {
+        let mut line = serde_json::to_vec(exchange)?;
+        line.push(b'\n');
+        let mut output = self.output.lock().unwrap();
+        output.write_all(&line)?;
+        output.flush()?;
+        Ok(())
+    }
+}
+
+impl<T> Transport for Recorder<T>
+where
+    T: Transport,
+{
+    fn request(&self, request: Request<Body>) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let request_body = hoover_body_to_vec(body).await?;
+            let (method, uri) = (parts.method.to_string(), redact_url(&parts.uri.to_string()));
+            let request_headers = redacted_headers(&parts.headers);
+            let request = Request::from_parts(parts, Body::from(request_body.clone()));
+
+            let response = self.inner.request(request).await?;
+            // An upgraded connection has no body to speak of, and reading
+            // it would spoil the upgrade
+            let (response, response_body) = if response.status() == StatusCode::SWITCHING_PROTOCOLS
+            {
+                (response, Vec::new())
+            } else {
+                let (parts, body) = response.into_parts();
+                let body = hoover_body_to_vec(body).await?;
+                (Response::from_parts(parts, Body::from(body.clone())), body)
+            };
+
+            self.write(&Exchange {
+                method,
+                uri,
+                request_headers,
+                request_body: redacted_body(&request_body),
+                status: response.status().as_u16(),
+                response_headers: redacted_headers(response.headers()),
+                response_body: redacted_body(&response_body),
+            })?;
+            Ok(response)
+        })
+    }
+}
+
+/// A transport which answers from a recording
+///
+/// Each request is answered with the first exchange not yet replayed which
+/// has the same method and URI, after the same redaction as the recording
+/// had.  A request which can't be answered is an error.
+pub struct Replay {
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl Replay {
+    /// Replay the given exchanges
+    pub fn new<I: IntoIterator<Item = Exchange>>(exchanges: I) -> Self {
+        Self {
+            exchanges: Mutex::new(exchanges.into_iter().collect()),
+        }
+    }
+
+    /// Replay the JSON-lines recording read from `input`
+    pub fn from_reader<R: BufRead>(input: R) -> Result<Self> {
+        let mut exchanges = Vec::new();
+        for line in input.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                exchanges.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(Self::new(exchanges))
+    }
+
+    /// Replay the JSON-lines recording in the file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// How many exchanges have yet to be replayed
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().len()
+    }
+
+    fn take(&self, method: &str, uri: &str) -> Result<Exchange> {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let index = exchanges
+            .iter()
+            .position(|exchange| exchange.method == method && exchange.uri == uri)
+            .ok_or_else(|| {
+                Error::protocol(
+                    "Replay",
+                    format!("No recorded response for {} {}", method, uri),
+                )
+            })?;
+        Ok(exchanges.remove(index).expect("Exchange vanished"))
+    }
+}
+
+impl Transport for Replay {
+    fn request(&self, request: Request<Body>) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let method = request.method().to_string();
+            let uri = redact_url(&request.uri().to_string());
+            // Drain the body, as a real server would
+            hoover_body_to_vec(request.into_body()).await?;
+            let exchange = self.take(&method, &uri)?;
+            let mut response = Response::builder();
+            response.status(exchange.status);
+            for (name, value) in &exchange.response_headers {
+                response.header(name.as_str(), value.as_str());
+            }
+            let body = exchange.response_body.to_bytes()?;
+            Ok(response.body(Body::from(body))?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{json, response};
+    use crate::testutil::*;
+    use hyper::Method;
+    use remsync_api_types::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn jwts_lose_their_signatures() {
+        let token = user_token(now_epoch_secs());
+        let redacted = redact_jwts(&format!("Bearer {}, and more", token));
+        let signature = token.rsplit('.').next().unwrap();
+        assert!(!redacted.contains(signature));
+        assert!(redacted.ends_with(".REDACTED, and more"));
+        assert_eq!(redact_jwts("eyJ but not a token"), "eyJ but not a token");
+    }
+
+    #[test]
+    fn signed_urls_are_redacted() {
+        let body = br#"[{"ID":"some-id","BlobURLGet":"https://blobs.example/x?a=1&sig=secret"}]"#;
+        match redacted_body(body) {
+            Payload::Text(text) => {
+                assert!(text.contains("https://blobs.example/x?a=1&sig=REDACTED"));
+                assert!(!text.contains("secret"));
+            }
+            other => panic!("Unexpected payload {:?}", other),
+        }
+    }
+
+    #[test]
+    fn registration_codes_are_redacted() {
+        let request = DeviceTokenRequest::new("abcdefgh", "desktop-linux", "some-device-id");
+        match redacted_body(&serde_json::to_vec(&request).unwrap()) {
+            Payload::Text(text) => {
+                assert!(text.contains(r#""code":"REDACTED""#));
+                assert!(text.contains("some-device-id"));
+                assert!(!text.contains("abcdefgh"));
+            }
+            other => panic!("Unexpected payload {:?}", other),
+        }
+    }
+
+    #[test]
+    fn credential_headers_are_dropped() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer secret".parse().unwrap());
+        headers.insert("Set-Cookie", "session=secret".parse().unwrap());
+        headers.insert("Content-Type", "application/json".parse().unwrap());
+        assert_eq!(
+            redacted_headers(&headers),
+            vec![("content-type".to_owned(), "application/json".to_owned())]
+        );
+    }
+
+    /// A writer which can be inspected after the recorder has it
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn recordings_replay() {
+        let transport = fake_cloud();
+        transport.route(
+            Method::GET,
+            STORAGE_HOST,
+            "/document-storage/json/2/docs",
+            |_| {
+                json(&[DocsResponse::new(
+                    true,
+                    "",
+                    node_id(1),
+                    3,
+                    "https://blobs.example/get?signature=secret",
+                    "2999-01-01T00:00:00Z".parse().unwrap(),
+                    "2019-09-01T10:29:26.440Z".parse().unwrap(),
+                    NodeType::DocumentType,
+                    "My Shiny Node",
+                    0,
+                    false,
+                    ParentRef::Root,
+                )])
+            },
+        );
+        transport.route(Method::GET, "blobs.example", "/get", |_| {
+            response(StatusCode::OK, vec![0xff, 0x00, 0x80])
+        });
+        let recording = Shared::default();
+        let live = session(Recorder::new(transport, recording.clone()));
+        live.list().await.unwrap();
+        let mut blob = Vec::new();
+        live.fetch_blob_to(&node_id(1), &mut blob, &mut |_| {})
+            .await
+            .unwrap();
+
+        let recording = recording.0.lock().unwrap().clone();
+        let text = String::from_utf8(recording.clone()).unwrap();
+        assert!(!text.contains("secret"));
+        assert!(!text.contains("some-device-token"));
+        assert_eq!(text.lines().count(), 5);
+
+        let replay = session(Replay::from_reader(&recording[..]).unwrap());
+        let replayed: Vec<NodeId> = replay
+            .list()
+            .await
+            .unwrap()
+            .iter()
+            .map(|doc| doc.id().clone())
+            .collect();
+        assert_eq!(replayed, vec![node_id(1)]);
+        let mut replayed = Vec::new();
+        replay
+            .fetch_blob_to(&node_id(1), &mut replayed, &mut |_| {})
+            .await
+            .unwrap();
+        assert_eq!(replayed, blob);
+        assert_eq!(replay.client().remaining(), 0);
+    }
+}
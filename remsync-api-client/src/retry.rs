@@ -0,0 +1,242 @@
+//! Retrying transient failures
+//!
+//! The storage service and the blob store behind it occasionally fail
+//! requests which would succeed if simply tried again, either by resetting
+//! the connection or by replying with a 5xx status.  A [`RetryPolicy`] says
+//! how many times to try, how long to wait in between, and which failures
+//! are worth trying again.
+//!
+//! Not every request is safe to repeat, so it's up to the caller to decide
+//! where a policy is applied.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use hyper::StatusCode;
+use tokio::timer::delay;
+
+use crate::{Backoff, Error, Result};
+
+/// The statuses retried by default: timeouts, rate limiting, and the
+/// server errors which usually indicate an overloaded or restarting server
+const DEFAULT_RETRYABLE_STATUSES: &[StatusCode] = &[
+    StatusCode::REQUEST_TIMEOUT,
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// How to retry requests which fail transiently
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Backoff,
+    retryable_statuses: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+    /// Create a retry policy making at most `attempts` attempts in total
+    ///
+    /// The policy retries transport failures and the statuses 408, 429,
+    /// 500, 502, 503 and 504.  Use
+    /// [`with_retryable_statuses`](Self::with_retryable_statuses) to change
+    /// the statuses.
+    pub fn new(attempts: u32, backoff: Backoff) -> Self {
+        Self {
+            attempts: attempts.max(1),
+            backoff,
+            retryable_statuses: DEFAULT_RETRYABLE_STATUSES.to_vec(),
+        }
+    }
+
+    /// A policy which never retries
+    pub fn never() -> Self {
+        Self::new(1, Backoff::default())
+    }
+
+    /// Retry replies with these statuses rather than the defaults
+    pub fn with_retryable_statuses(mut self, statuses: &[StatusCode]) -> Self {
+        self.retryable_statuses = statuses.to_vec();
+        self
+    }
+
+    /// The most attempts which will be made, including the first
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// The backoff between attempts
+    pub fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    /// The HTTP statuses which are considered transient
+    pub fn retryable_statuses(&self) -> &[StatusCode] {
+        &self.retryable_statuses
+    }
+
+    /// Whether the given error is worth retrying under this policy
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error {
+            Error::Transport(_) => true,
+            Error::Status { status, .. } => self.retryable_statuses.contains(status),
+            _ => false,
+        }
+    }
+
+    /// Decide whether to make another attempt after attempt number
+    /// `attempt` (counting from 1) failed, waiting first if so
+    ///
+    /// `retryable` says whether the failure was of a kind worth retrying.
+    pub(crate) async fn again(&self, attempt: u32, retryable: bool) -> bool {
+        if !retryable || attempt >= self.attempts {
+            return false;
+        }
+        let wait = self.backoff.delay(attempt);
+        if wait > Duration::from_secs(0) {
+            delay(Instant::now() + wait).await;
+        }
+        true
+    }
+
+    /// Run `op` until it succeeds, it fails in a way `should_retry` rejects,
+    /// or we run out of attempts
+    ///
+    /// The argument given to `op` is the number of the attempt, from 1.
+    pub(crate) async fn run<F, Fut, T, P>(&self, mut op: F, mut should_retry: P) -> Result<T>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T>>,
+        P: FnMut(&Error) -> bool,
+    {
+        let mut attempt = 1;
+        loop {
+            let e = match op(attempt).await {
+                Ok(ret) => return Ok(ret),
+                Err(e) => e,
+            };
+            if !self.again(attempt, should_retry(&e)).await {
+                return Err(e);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Four attempts, starting one second apart
+    fn default() -> Self {
+        Self::new(
+            4,
+            Backoff::new(Duration::from_secs(1), Duration::from_secs(30)),
+        )
+    }
+}
+
+/// Whether the blob store rejected a signed URL, either because it had
+/// expired or because it had already been used, or a fetch has to start
+/// again with a fresh URL
+///
+/// Retrying with the same URL is pointless, but fetching a fresh one and
+/// trying again is worthwhile.
+pub(crate) fn is_blob_url_rejection(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::BlobUrlExpired { .. } | Error::BlobUrlConsumed { .. } | Error::BlobRestart { .. }
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    fn status(api: &'static str, status: StatusCode) -> Error {
+        Error::Status {
+            api,
+            status,
+            body: String::new(),
+        }
+    }
+
+    fn quick(attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(
+            attempts,
+            Backoff::new(Duration::from_millis(1), Duration::from_millis(1)),
+        )
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable(&status("GetDocsList", StatusCode::BAD_GATEWAY)));
+        assert!(!policy.is_retryable(&status("GetDocsList", StatusCode::NOT_FOUND)));
+        assert!(!policy.is_retryable(&Error::protocol("GetDocsList", "oops")));
+        assert!(is_blob_url_rejection(&Error::BlobUrlExpired {
+            api: "GetBlob"
+        }));
+        assert!(!is_blob_url_rejection(&status(
+            "GetBlob",
+            StatusCode::FORBIDDEN
+        )));
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let calls = Cell::new(0);
+        let policy = quick(3);
+        let ret = policy
+            .run(
+                |attempt| {
+                    calls.set(attempt);
+                    async move {
+                        if attempt < 3 {
+                            Err(status("GetDocsList", StatusCode::SERVICE_UNAVAILABLE))
+                        } else {
+                            Ok(attempt)
+                        }
+                    }
+                },
+                |e| policy.is_retryable(e),
+            )
+            .await;
+        assert_eq!(ret.unwrap(), 3);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn attempts_are_limited() {
+        let calls = Cell::new(0);
+        let policy = quick(2);
+        let ret: Result<()> = policy
+            .run(
+                |attempt| {
+                    calls.set(attempt);
+                    async { Err(status("GetDocsList", StatusCode::SERVICE_UNAVAILABLE)) }
+                },
+                |e| policy.is_retryable(e),
+            )
+            .await;
+        assert!(ret.is_err());
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn permanent_failures_are_not_retried() {
+        let calls = Cell::new(0);
+        let policy = quick(3);
+        let ret: Result<()> = policy
+            .run(
+                |attempt| {
+                    calls.set(attempt);
+                    async { Err(status("GetDocsList", StatusCode::NOT_FOUND)) }
+                },
+                |e| policy.is_retryable(e),
+            )
+            .await;
+        assert!(ret.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
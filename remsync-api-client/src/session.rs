@@ -0,0 +1,1173 @@
+//! A stateful session with the reMarkable cloud storage API
+//!
+//! The functions in [`ll`](crate::ll) need the client, the base URI of the
+//! service, and a bearer token passing to every call.  A [`StorageSession`]
+//! holds all of that for you, acquiring a user token and discovering the
+//! storage host the first time they are needed.
+//!
+//! User tokens are kept in a [`TokenCache`] and transparently refreshed if
+//! the storage service rejects them.
+//!
+//! Requests which are safe to repeat are retried according to the session's
+//! [`RetryPolicy`]: lookups always, and uploads until the blob store has
+//! acknowledged the blob.
+//!
+//! The session can also connect to the notification service, yielding a
+//! [`NotificationStream`] of changes made by other devices.
+
+use std::cell::Cell;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::Uri;
+use remsync_api_types::*;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncWrite, BufWriter};
+
+use crate::jwt::TokenVerifier;
+use crate::notifications::reconnecting;
+use crate::retry::is_blob_url_rejection;
+use crate::util::single_result;
+use crate::{
+    ll, Backoff, BlobUrl, Error, NotificationSocket, NotificationStream, Profile, Progress, Result,
+    RetryPolicy, TokenCache, Transport,
+};
+
+/// How long a blob URL must have left before we'll start a download with it
+const BLOB_URL_MARGIN: Duration = Duration::from_secs(30);
+
+/// The metadata to change in a node, leaving anything unset as it was
+#[derive(Default)]
+struct NodeChange<'a> {
+    parent: Option<&'a ParentRef>,
+    name: Option<&'a str>,
+    bookmarked: Option<bool>,
+    current_page: Option<usize>,
+}
+
+/// Run `$body` with a user token and storage base, retrying once with a
+/// fresh user token if the server says the one we had is no good.
+macro_rules! with_user_token {
+    ($self:ident, |$token:ident, $base:ident| $body:expr) => {{
+        let $base = $self.storage_base().await?;
+        let $token = $self.user_token().await?;
+        match $body.await {
+            Err(ref e) if e.is_unauthorized() => {
+                $self.token_cache.invalidate(&$self.device_token)?;
+                let $token = $self.user_token().await?;
+                $body.await
+            }
+            other => other,
+        }
+    }};
+}
+
+/// A session with the document storage service
+///
+/// The session is created from a device token and lazily acquires a user
+/// token and discovers the storage host as they are needed.  The storage
+/// host is remembered for the lifetime of the session, and the user token
+/// is held in the session's token cache until it is close to expiry.
+pub struct StorageSession<T> {
+    client: T,
+    auth_base: Uri,
+    discovery_base: Uri,
+    device_token: String,
+    token_cache: Arc<TokenCache>,
+    verifier: Option<TokenVerifier>,
+    environment: String,
+    storage_api_version: u32,
+    notifications_api_version: u32,
+    retry: RetryPolicy,
+    lenient_ids: bool,
+    storage_base: Mutex<Option<Uri>>,
+    notifications_base: Mutex<Option<Uri>>,
+}
+
+impl<T> StorageSession<T>
+where
+    T: Transport,
+{
+    /// Create a new session
+    ///
+    /// The `auth_base` is the base URI of the authentication service and
+    /// the `discovery_base` is the base URI of the service manager.  No
+    /// network traffic happens until the session is first used.
+    ///
+    /// The session starts with an in-memory token cache, use
+    /// [`with_token_cache`](Self::with_token_cache) to share a cache
+    /// or to persist tokens to disk.
+    pub fn new(client: T, auth_base: Uri, discovery_base: Uri, device_token: &str) -> Self {
+        Self {
+            client,
+            auth_base,
+            discovery_base,
+            device_token: device_token.to_owned(),
+            token_cache: Arc::new(TokenCache::in_memory()),
+            verifier: None,
+            environment: ll::DEFAULT_ENVIRONMENT.to_owned(),
+            storage_api_version: ll::STORAGE_API_VERSION,
+            notifications_api_version: ll::NOTIFICATIONS_API_VERSION,
+            retry: RetryPolicy::default(),
+            lenient_ids: false,
+            storage_base: Mutex::new(None),
+            notifications_base: Mutex::new(None),
+        }
+    }
+
+    /// Create a new session against the deployment described by `profile`
+    pub fn for_profile(client: T, profile: &Profile, device_token: &str) -> Self {
+        Self::new(
+            client,
+            profile.auth_base().clone(),
+            profile.discovery_base().clone(),
+            device_token,
+        )
+        .with_environment(profile.environment())
+        .with_api_versions(
+            profile.storage_api_version(),
+            profile.notifications_api_version(),
+        )
+    }
+
+    /// Use the given token cache for user tokens
+    pub fn with_token_cache(mut self, token_cache: Arc<TokenCache>) -> Self {
+        self.token_cache = token_cache;
+        self
+    }
+
+    /// Verify the device token and user tokens with `verifier` before use
+    ///
+    /// Without a verifier, a bad token is only noticed when the server
+    /// rejects it.  Cached user tokens which fail verification are
+    /// replaced.
+    pub fn with_token_verifier(mut self, verifier: TokenVerifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Use the given environment when discovering services
+    ///
+    /// This defaults to `production`, as used by the official clients.
+    pub fn with_environment(mut self, environment: &str) -> Self {
+        self.environment = environment.to_owned();
+        self
+    }
+
+    /// Use the given `apiVer` values when discovering the storage and
+    /// notification services
+    pub fn with_api_versions(mut self, storage: u32, notifications: u32) -> Self {
+        self.storage_api_version = storage;
+        self.notifications_api_version = notifications;
+        self
+    }
+
+    /// Use the given policy to retry transient failures
+    ///
+    /// This defaults to [`RetryPolicy::default`].
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The policy used to retry transient failures
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Accept node IDs which are not UUIDs in replies and notifications
+    ///
+    /// Servers other than the official one may hand out such IDs.  This
+    /// defaults to off, rejecting them as [`NodeId::parse`] does.
+    pub fn with_lenient_ids(mut self, lenient: bool) -> Self {
+        self.lenient_ids = lenient;
+        self
+    }
+
+    /// Whether a failure to fetch or put a blob is worth retrying, given
+    /// that each attempt requests a fresh signed URL
+    fn blob_retryable(&self, error: &Error) -> bool {
+        self.retry.is_retryable(error) || is_blob_url_rejection(error)
+    }
+
+    /// The transport used by this session
+    pub fn client(&self) -> &T {
+        &self.client
+    }
+
+    /// The device token this session authenticates with
+    pub fn device_token(&self) -> &str {
+        &self.device_token
+    }
+
+    /// Retrieve the user token, acquiring one if the cache has no usable token
+    pub async fn user_token(&self) -> Result<String> {
+        if let Some(token) = self.token_cache.get(&self.device_token) {
+            if self.verify_user_token(&token).is_ok() {
+                return Ok(token);
+            }
+        }
+        if let Some(verifier) = &self.verifier {
+            verifier.verify_device_token(&self.device_token)?;
+        }
+        let token =
+            ll::auth_get_user_bearer(&self.client, &self.auth_base, &self.device_token).await?;
+        self.verify_user_token(&token)?;
+        self.token_cache.insert(&self.device_token, &token)?;
+        Ok(token)
+    }
+
+    fn verify_user_token(&self, token: &str) -> Result<()> {
+        match &self.verifier {
+            Some(verifier) => verifier.verify_user_token(token).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Retrieve the base URI of the storage service, discovering it if needed
+    pub async fn storage_base(&self) -> Result<Uri> {
+        if let Some(base) = self.storage_base.lock().unwrap().as_ref() {
+            return Ok(base.clone());
+        }
+        let user_token = self.user_token().await?;
+        let host = self
+            .retry
+            .run(
+                |_| {
+                    ll::discover_storage_service(
+                        &self.client,
+                        &self.discovery_base,
+                        &user_token,
+                        &self.environment,
+                        self.storage_api_version,
+                    )
+                },
+                |e| self.retry.is_retryable(e),
+            )
+            .await?;
+        let base: Uri = format!("https://{}/", host).parse()?;
+        *self.storage_base.lock().unwrap() = Some(base.clone());
+        Ok(base)
+    }
+
+    /// Retrieve the base URI of the notification service, discovering it if needed
+    pub async fn notifications_base(&self) -> Result<Uri> {
+        if let Some(base) = self.notifications_base.lock().unwrap().as_ref() {
+            return Ok(base.clone());
+        }
+        let user_token = self.user_token().await?;
+        let host = self
+            .retry
+            .run(
+                |_| {
+                    ll::discover_notification_service(
+                        &self.client,
+                        &self.discovery_base,
+                        &user_token,
+                        &self.environment,
+                        self.notifications_api_version,
+                    )
+                },
+                |e| self.retry.is_retryable(e),
+            )
+            .await?;
+        let base: Uri = format!("https://{}/", host).parse()?;
+        *self.notifications_base.lock().unwrap() = Some(base.clone());
+        Ok(base)
+    }
+
+    /// Connect to the notification service
+    ///
+    /// The returned socket is a single connection, see
+    /// [`notifications`](Self::notifications) for a stream of events which
+    /// reconnects as needed.
+    pub async fn connect_notifications(&self) -> Result<NotificationSocket> {
+        let base = self.notifications_base().await?;
+        let user_token = self.user_token().await?;
+        let socket = match ll::notifications_connect(&self.client, &base, &user_token).await {
+            Err(ref e) if e.is_unauthorized() => {
+                self.token_cache.invalidate(&self.device_token)?;
+                let user_token = self.user_token().await?;
+                ll::notifications_connect(&self.client, &base, &user_token).await
+            }
+            other => other,
+        };
+        Ok(socket?.with_lenient_ids(self.lenient_ids))
+    }
+
+    /// A stream of notification events, reconnecting with the default
+    /// [`Backoff`] whenever the connection is lost
+    pub fn notifications(&self) -> NotificationStream<'_> {
+        self.notifications_with_backoff(Backoff::default())
+    }
+
+    /// A stream of notification events, reconnecting with the given
+    /// [`Backoff`] whenever the connection is lost
+    pub fn notifications_with_backoff(&self, backoff: Backoff) -> NotificationStream<'_> {
+        reconnecting(move || self.connect_notifications(), backoff)
+    }
+
+    /// List every node in the storage service
+    pub async fn list(&self) -> Result<Vec<DocsResponse>> {
+        self.retry
+            .run(|_| self.list_once(), |e| self.retry.is_retryable(e))
+            .await
+    }
+
+    async fn list_once(&self) -> Result<Vec<DocsResponse>> {
+        with_user_token!(self, |user_token, base| ll::storage_fetch_all_docs(
+            &self.client,
+            &base,
+            &user_token,
+            self.lenient_ids
+        ))
+    }
+
+    /// Retrieve the metadata for a single node, if the server has it
+    ///
+    /// Unlike fetching the blob, this doesn't mint a signed URL, so it's a
+    /// cheap way to check a node's current version before changing it.
+    pub async fn fetch_doc(&self, id: &NodeId) -> Result<Option<DocsResponse>> {
+        self.retry
+            .run(|_| self.fetch_doc_once(id), |e| self.retry.is_retryable(e))
+            .await
+    }
+
+    async fn fetch_doc_once(&self, id: &NodeId) -> Result<Option<DocsResponse>> {
+        with_user_token!(self, |user_token, base| ll::storage_fetch_doc(
+            &self.client,
+            &base,
+            &user_token,
+            id,
+            self.lenient_ids
+        ))
+    }
+
+    /// Retrieve a fresh signed URL from which a node's blob can be fetched
+    ///
+    /// The URL can be handed to anything which speaks HTTP, so the
+    /// download can happen whenever and however suits the caller, as long
+    /// as it's before the URL expires.  To download it later through the
+    /// session, use [`fetch_blob_from_url`](Self::fetch_blob_from_url).
+    pub async fn fetch_blob_url(&self, id: &NodeId) -> Result<BlobUrl> {
+        self.retry
+            .run(
+                |_| self.fetch_blob_url_once(id),
+                |e| self.retry.is_retryable(e),
+            )
+            .await
+    }
+
+    async fn fetch_blob_url_once(&self, id: &NodeId) -> Result<BlobUrl> {
+        with_user_token!(self, |user_token, base| ll::storage_fetch_blob_url(
+            &self.client,
+            &base,
+            &user_token,
+            id,
+            self.lenient_ids
+        ))
+    }
+
+    /// Fetch the blob for the given node, writing it to `output`
+    ///
+    /// Failures are retried until the first byte has been written out,
+    /// after which it's too late.  Use
+    /// [`fetch_blob_file`](Self::fetch_blob_file) to retry at any point.
+    /// Returns the number of bytes written
+    pub async fn fetch_blob(&self, id: &NodeId, output: &mut dyn std::io::Write) -> Result<usize> {
+        let mut output = CountingWrite {
+            inner: output,
+            written: 0,
+        };
+        let mut attempt = 1;
+        loop {
+            let e = match self.fetch_blob_once(id, &mut output).await {
+                Ok(written) => return Ok(written),
+                Err(e) => e,
+            };
+            let retryable = output.written == 0 && self.blob_retryable(&e);
+            if !self.retry.again(attempt, retryable).await {
+                return Err(e);
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn fetch_blob_once(&self, id: &NodeId, output: &mut dyn std::io::Write) -> Result<usize> {
+        // An authorisation failure happens before anything is written out
+        // so it's safe to retry the whole fetch
+        with_user_token!(self, |user_token, base| ll::storage_fetch_blob(
+            &self.client,
+            &base,
+            &user_token,
+            id,
+            self.lenient_ids,
+            output
+        ))
+    }
+
+    /// Fetch the blob for the given node, writing it asynchronously to
+    /// `output`
+    ///
+    /// The `progress` callback is called as each chunk arrives.  To report
+    /// progress over a channel instead, send from within the callback.
+    /// Failures are retried until the first byte has been written out.
+    /// Returns the number of bytes written
+    pub async fn fetch_blob_to<W>(
+        &self,
+        id: &NodeId,
+        output: W,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.get_blob_to(id, None, output, progress).await
+    }
+
+    /// Fetch the blob for the given node from a URL previously returned by
+    /// [`fetch_blob_url`](Self::fetch_blob_url), writing it asynchronously
+    /// to `output`
+    ///
+    /// If the URL has expired, or is about to, a fresh one is requested
+    /// first; likewise if the blob store rejects it.  Otherwise this is
+    /// just like [`fetch_blob_to`](Self::fetch_blob_to).
+    pub async fn fetch_blob_from_url<W>(
+        &self,
+        id: &NodeId,
+        url: BlobUrl,
+        output: W,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.get_blob_to(id, Some(url), output, progress).await
+    }
+
+    async fn get_blob_to<W>(
+        &self,
+        id: &NodeId,
+        mut url: Option<BlobUrl>,
+        mut output: W,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let written = Cell::new(0);
+        let mut progress = |p: Progress| {
+            written.set(p.transferred());
+            progress(p)
+        };
+        let mut attempt = 1;
+        loop {
+            // Only looking up the blob URL needs our user token, and each
+            // retry gets a fresh URL in case the last one was rejected
+            let url = match url.take() {
+                Some(url) if !url.expires_within(BLOB_URL_MARGIN) => Ok(url),
+                _ => self.fetch_blob_url_once(id).await,
+            };
+            let ret = match url {
+                Ok(url) => {
+                    ll::storage_get_blob_to(&self.client, &url, &mut output, &mut progress).await
+                }
+                Err(e) => Err(e),
+            };
+            let e = match ret {
+                Ok(written) => return Ok(written),
+                Err(e) => e,
+            };
+            let retryable = written.get() == 0 && self.blob_retryable(&e);
+            if !self.retry.again(attempt, retryable).await {
+                return Err(e);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Fetch the blob for the given node into the file at `path`
+    ///
+    /// The file is created afresh for each attempt, so unlike
+    /// [`fetch_blob_to`](Self::fetch_blob_to) a failure partway through
+    /// the blob can still be retried.  Returns the number of bytes written
+    pub async fn fetch_blob_file<P>(
+        &self,
+        id: &NodeId,
+        path: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut attempt = 1;
+        loop {
+            let ret = match self.fetch_blob_url_once(id).await {
+                Ok(url) => match File::create(path).await {
+                    Ok(file) => {
+                        let output = BufWriter::new(file);
+                        ll::storage_get_blob_to(&self.client, &url, output, progress).await
+                    }
+                    Err(e) => Err(e.into()),
+                },
+                Err(e) => Err(e),
+            };
+            let e = match ret {
+                Ok(written) => return Ok(written),
+                Err(e) => e,
+            };
+            if !self.retry.again(attempt, self.blob_retryable(&e)).await {
+                return Err(e);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Fetch the blob for the given node into the file at `path`, picking
+    /// up where an earlier, interrupted fetch left off
+    ///
+    /// Whatever is already in the file is assumed to be the start of this
+    /// blob, so give each version of a node its own file.  Failures are
+    /// retried, each attempt resuming from what has been written so far.
+    /// Returns the size of the whole blob, along with the size the blob
+    /// host reported for it, if it did
+    pub async fn resume_blob_file<P>(
+        &self,
+        id: &NodeId,
+        path: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<(u64, Option<u64>)>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut attempt = 1;
+        loop {
+            let ret = match self.fetch_blob_url_once(id).await {
+                Ok(url) => ll::storage_resume_blob_file(&self.client, &url, path, progress).await,
+                Err(e) => Err(e),
+            };
+            let e = match ret {
+                Ok(size) => return Ok(size),
+                Err(e) => e,
+            };
+            if !self.retry.again(attempt, self.blob_retryable(&e)).await {
+                return Err(e);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Upload a node's blob and then update its metadata to match `node`
+    ///
+    /// Returns the number of bytes uploaded
+    pub async fn upload(&self, node: &UpdateStatusRequest, zipfile: Vec<u8>) -> Result<usize> {
+        // Until the blob store acknowledges the blob nothing is visible, so
+        // the request and the blob put can be retried together, getting a
+        // fresh signed URL each time
+        let sent = self
+            .retry
+            .run(
+                |_| self.put_blob_once(node, zipfile.clone()),
+                |e| self.blob_retryable(e),
+            )
+            .await?;
+        self.update(node).await?;
+        Ok(sent)
+    }
+
+    /// Request an upload for `node`, without retrying
+    ///
+    /// The callers retry the request along with the blob put which follows
+    /// it, so retrying here as well would multiply the attempts.
+    async fn request_upload_once(
+        &self,
+        node: &UpdateStatusRequest,
+    ) -> Result<UploadRequestResponse> {
+        single_result(self.request_uploads_once(&upload_request(node)).await?)
+    }
+
+    async fn put_blob_once(&self, node: &UpdateStatusRequest, zipfile: Vec<u8>) -> Result<usize> {
+        let reply = self.request_upload_once(node).await?;
+        ll::storage_put_blob(&self.client, &BlobUrl::for_upload(&reply)?, zipfile).await
+    }
+
+    /// Upload a node's blob, streaming exactly `length` bytes from `reader`,
+    /// and then update its metadata to match `node`
+    ///
+    /// The `progress` callback is called as each chunk of the blob is sent.
+    /// The reader can't be rewound, so once the blob put has started it is
+    /// not retried; use [`upload_file`](Self::upload_file) where possible.
+    /// Returns the number of bytes uploaded
+    pub async fn upload_from<R>(
+        &self,
+        node: &UpdateStatusRequest,
+        reader: R,
+        length: u64,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+    {
+        // The blob put uses a signed URL rather than our user token, so
+        // only the requests either side of it need to cope with an expired
+        // token.
+        let reply = single_result(self.request_uploads(&upload_request(node)).await?)?;
+        let url = BlobUrl::for_upload(&reply)?;
+        let sent = ll::storage_put_blob_from(&self.client, &url, reader, length, progress).await?;
+        self.update(node).await?;
+        Ok(sent)
+    }
+
+    /// Upload a node's blob from the file at `path`, and then update its
+    /// metadata to match `node`
+    ///
+    /// The `progress` callback is called as each chunk of the blob is sent.
+    /// The file is reopened for each attempt, so failures are retried until
+    /// the blob store acknowledges the blob.  Returns the number of bytes
+    /// uploaded
+    pub async fn upload_file<P>(
+        &self,
+        node: &UpdateStatusRequest,
+        path: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut attempt = 1;
+        let sent = loop {
+            let e = match self.put_file_once(node, path, progress).await {
+                Ok(sent) => break sent,
+                Err(e) => e,
+            };
+            if !self.retry.again(attempt, self.blob_retryable(&e)).await {
+                return Err(e);
+            }
+            attempt += 1;
+        };
+        self.update(node).await?;
+        Ok(sent)
+    }
+
+    async fn put_file_once(
+        &self,
+        node: &UpdateStatusRequest,
+        path: &Path,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<u64> {
+        let file = File::open(path).await?;
+        let length = file.metadata().await?.len();
+        let url = BlobUrl::for_upload(&self.request_upload_once(node).await?)?;
+        ll::storage_put_blob_from(&self.client, &url, file, length, progress).await
+    }
+
+    /// Update a node's metadata without sending a blob
+    pub async fn update(&self, node: &UpdateStatusRequest) -> Result<()> {
+        let ret = with_user_token!(self, |user_token, base| ll::storage_update_docs(
+            &self.client,
+            &base,
+            &user_token,
+            std::slice::from_ref(node),
+        ))?;
+        single_result(ret)?;
+        Ok(())
+    }
+
+    /// Rename a node, returning its new version
+    pub async fn rename_node(&self, id: &NodeId, name: &str) -> Result<usize> {
+        let change = NodeChange {
+            name: Some(name),
+            ..NodeChange::default()
+        };
+        self.change_node(id, change).await
+    }
+
+    /// Move a node into `parent`, returning its new version
+    pub async fn move_node(&self, id: &NodeId, parent: &ParentRef) -> Result<usize> {
+        let change = NodeChange {
+            parent: Some(parent),
+            ..NodeChange::default()
+        };
+        self.change_node(id, change).await
+    }
+
+    /// Bookmark or unbookmark a node, returning its new version
+    pub async fn set_bookmarked(&self, id: &NodeId, bookmarked: bool) -> Result<usize> {
+        let change = NodeChange {
+            bookmarked: Some(bookmarked),
+            ..NodeChange::default()
+        };
+        self.change_node(id, change).await
+    }
+
+    /// Set the page a document is open at, returning its new version
+    pub async fn set_current_page(&self, id: &NodeId, current_page: usize) -> Result<usize> {
+        let change = NodeChange {
+            current_page: Some(current_page),
+            ..NodeChange::default()
+        };
+        self.change_node(id, change).await
+    }
+
+    /// Apply `change` on top of the server's current metadata for a node
+    ///
+    /// The update carries the next version, so if another device changes
+    /// the node in the meantime the server reports a version conflict
+    /// rather than losing either change.
+    async fn change_node(&self, id: &NodeId, change: NodeChange<'_>) -> Result<usize> {
+        let doc = self
+            .fetch_doc(id)
+            .await?
+            .ok_or_else(|| Error::protocol("GetDocsList", format!("No such node {}", id)))?;
+        let node = UpdateStatusRequest::new(
+            doc.id().clone(),
+            change.parent.unwrap_or(doc.parent()).clone(),
+            doc.node_type().clone(),
+            doc.version() + 1,
+            change.bookmarked.unwrap_or(doc.bookmarked()),
+            change.current_page.unwrap_or(doc.current_page()),
+            change.name.unwrap_or(doc.name()),
+            doc.modified_client(),
+        )
+        .with_unknown_fields(doc.unknown_fields().clone());
+        self.update(&node).await?;
+        Ok(node.version())
+    }
+
+    /// The server's current version of a node which a change to was
+    /// refused with `error`
+    ///
+    /// Not every version conflict says what the server holds, so if this
+    /// one doesn't the node is fetched to find out.  Returns `None` if
+    /// `error` isn't a version conflict, or if the server no longer has the
+    /// node.
+    pub async fn conflict_version(&self, id: &NodeId, error: &Error) -> Result<Option<usize>> {
+        if !error.is_version_conflict() {
+            return Ok(None);
+        }
+        if let Some(version) = error.conflict_version() {
+            return Ok(Some(version));
+        }
+        Ok(self.fetch_doc(id).await?.map(|doc| doc.version()))
+    }
+
+    /// Delete a node, which must currently be at `version` on the server
+    pub async fn delete(&self, id: &NodeId, version: usize) -> Result<()> {
+        with_user_token!(self, |user_token, base| ll::storage_delete_doc(
+            &self.client,
+            &base,
+            &user_token,
+            id,
+            version
+        ))
+    }
+
+    /// Delete many nodes in one request
+    ///
+    /// The outer result reports failure of the request as a whole, the
+    /// inner results report the outcome for each node in turn.
+    pub async fn delete_many(&self, reqs: &[DeleteRequest]) -> Result<Vec<Result<DeleteResponse>>> {
+        with_user_token!(self, |user_token, base| ll::storage_delete_docs(
+            &self.client,
+            &base,
+            &user_token,
+            reqs
+        ))
+    }
+
+    /// Update the metadata of many nodes in one request
+    ///
+    /// The outer result reports failure of the request as a whole, the
+    /// inner results report the outcome for each node in turn.
+    pub async fn update_many(
+        &self,
+        reqs: &[UpdateStatusRequest],
+    ) -> Result<Vec<Result<UpdateStatusResponse>>> {
+        with_user_token!(self, |user_token, base| ll::storage_update_docs(
+            &self.client,
+            &base,
+            &user_token,
+            reqs
+        ))
+    }
+
+    /// Request uploads for many nodes in one request
+    ///
+    /// Each successful reply carries the URL to put that node's blob to,
+    /// after which the node must be updated to complete the upload.
+    /// Nothing changes on the server until then, so the request is retried
+    /// on transient failures.
+    pub async fn request_uploads(
+        &self,
+        reqs: &[UploadRequestRequest],
+    ) -> Result<Vec<Result<UploadRequestResponse>>> {
+        self.retry
+            .run(
+                |_| self.request_uploads_once(reqs),
+                |e| self.retry.is_retryable(e),
+            )
+            .await
+    }
+
+    async fn request_uploads_once(
+        &self,
+        reqs: &[UploadRequestRequest],
+    ) -> Result<Vec<Result<UploadRequestResponse>>> {
+        with_user_token!(self, |user_token, base| ll::storage_upload_requests(
+            &self.client,
+            &base,
+            &user_token,
+            reqs
+        ))
+    }
+}
+
+/// The upload request which goes before putting `node`'s blob
+fn upload_request(node: &UpdateStatusRequest) -> [UploadRequestRequest; 1] {
+    [UploadRequestRequest::new(
+        node.id().clone(),
+        node.parent().clone(),
+        node.node_type().clone(),
+        node.version(),
+    )]
+}
+
+/// A writer which counts how much has been written through it
+struct CountingWrite<'a> {
+    inner: &'a mut dyn std::io::Write,
+    written: usize,
+}
+
+impl std::io::Write for CountingWrite<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{json, response};
+    use crate::testutil::*;
+    use hyper::{Method, StatusCode};
+
+    const DOCS: &str = "/document-storage/json/2/docs";
+    const UPLOAD_REQUEST: &str = "/document-storage/json/2/upload/request";
+    const UPDATE_STATUS: &str = "/document-storage/json/2/upload/update-status";
+    const USER_TOKEN: &str = "/token/json/2/user/new";
+
+    fn node() -> UpdateStatusRequest {
+        UpdateStatusRequest::new(
+            node_id(1),
+            ParentRef::Root,
+            NodeType::DocumentType,
+            1,
+            false,
+            0,
+            "My Shiny Node",
+            &"2019-09-01T10:29:26.440Z".parse().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn list_retries_transient_failures() {
+        let transport = fake_cloud();
+        let calls = Calls::default();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, move |_| {
+            if calls.next() < 3 {
+                response(StatusCode::SERVICE_UNAVAILABLE, "")
+            } else {
+                json(&Vec::<DocsResponse>::new())
+            }
+        });
+        let session = session(transport);
+        assert!(session.list().await.unwrap().is_empty());
+        assert_eq!(session.client().count(DOCS), 3);
+        assert_eq!(session.client().count(USER_TOKEN), 1);
+    }
+
+    #[tokio::test]
+    async fn rejected_user_token_is_refreshed() {
+        let transport = fake_cloud();
+        let calls = Calls::default();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, move |_| {
+            if calls.next() == 1 {
+                response(StatusCode::UNAUTHORIZED, "")
+            } else {
+                json(&Vec::<DocsResponse>::new())
+            }
+        });
+        let session = session(transport);
+        session.list().await.unwrap();
+        assert_eq!(session.client().count(USER_TOKEN), 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_blob_url_is_requested_again() {
+        let transport = fake_cloud();
+        let calls = Calls::default();
+        transport.route(Method::PUT, STORAGE_HOST, UPLOAD_REQUEST, move |_| {
+            let url = format!("https://blobs.example/put/{}", calls.next());
+            json(&[UploadRequestResponse::new(
+                true,
+                "",
+                Some(node_id(1)),
+                1,
+                &url,
+                Timestamp::zero(),
+            )])
+        });
+        transport.route(Method::PUT, "blobs.example", "/put/1", |_| {
+            response(StatusCode::FORBIDDEN, "Request has expired")
+        });
+        transport.route(Method::PUT, "blobs.example", "/put/2", |_| {
+            response(StatusCode::OK, "")
+        });
+        transport.route(Method::PUT, STORAGE_HOST, UPDATE_STATUS, |_| {
+            json(&[UpdateStatusResponse::new(true, "", Some(node_id(1)), 1)])
+        });
+        let session = session(transport);
+        assert_eq!(session.upload(&node(), b"zip".to_vec()).await.unwrap(), 3);
+        assert_eq!(session.client().count(UPLOAD_REQUEST), 2);
+        assert_eq!(session.client().count("/put/2"), 1);
+        assert_eq!(session.client().count(UPDATE_STATUS), 1);
+    }
+
+    #[tokio::test]
+    async fn upload_attempts_are_not_multiplied() {
+        let transport = fake_cloud();
+        transport.route(Method::PUT, STORAGE_HOST, UPLOAD_REQUEST, |_| {
+            response(StatusCode::SERVICE_UNAVAILABLE, "")
+        });
+        let session = session(transport);
+        let err = session.upload(&node(), b"zip".to_vec()).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(session.client().count(UPLOAD_REQUEST), 3);
+
+        let path = std::env::temp_dir().join(format!("remsync-upload-{}", std::process::id()));
+        std::fs::write(&path, b"zip").unwrap();
+        let err = session.upload_file(&node(), &path, &mut |_| {}).await;
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.is_err());
+        assert_eq!(session.client().count(UPLOAD_REQUEST), 6);
+        assert_eq!(session.client().count(UPDATE_STATUS), 0);
+    }
+
+    fn blob_doc(url: &str) -> DocsResponse {
+        DocsResponse::new(
+            true,
+            "",
+            node_id(1),
+            3,
+            url,
+            "2999-01-01T00:00:00Z".parse().unwrap(),
+            "2019-09-01T10:29:26.440Z".parse().unwrap(),
+            NodeType::DocumentType,
+            "My Shiny Node",
+            0,
+            false,
+            ParentRef::Root,
+        )
+    }
+
+    #[tokio::test]
+    async fn lenient_ids_are_chosen_per_session() {
+        let body = serde_json::to_string(&[blob_doc("")])
+            .unwrap()
+            .replace(node_id(1).as_str(), "RM102-928-57210");
+        let listing = |lenient| {
+            let transport = fake_cloud();
+            let body = body.clone();
+            transport.route(Method::GET, STORAGE_HOST, DOCS, move |_| {
+                response(StatusCode::OK, body.clone())
+            });
+            session(transport).with_lenient_ids(lenient)
+        };
+        assert!(listing(false).list().await.is_err());
+        let docs = listing(true).list().await.unwrap();
+        assert_eq!(docs[0].id(), "RM102-928-57210");
+    }
+
+    #[tokio::test]
+    async fn expired_blob_url_is_reminted() {
+        let transport = fake_cloud();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, |_| {
+            json(&[blob_doc("https://blobs.example/get/fresh")])
+        });
+        transport.route(Method::GET, "blobs.example", "/get/fresh", |_| {
+            response(StatusCode::OK, "blob")
+        });
+        let session = session(transport);
+        let stale = BlobUrl::new(
+            "https://blobs.example/get/stale",
+            Some(std::time::SystemTime::now()),
+        );
+        let mut output = Vec::new();
+        let fetched = session
+            .fetch_blob_from_url(&node_id(1), stale, &mut output, &mut |_| {})
+            .await
+            .unwrap();
+        assert_eq!(fetched, 4);
+        assert_eq!(output, b"blob");
+        assert_eq!(session.client().count(DOCS), 1);
+        assert_eq!(session.client().count("/get/stale"), 0);
+    }
+
+    #[tokio::test]
+    async fn consumed_blob_url_is_reported() {
+        let transport = fake_cloud();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, |_| {
+            json(&[blob_doc("https://blobs.example/get/once")])
+        });
+        transport.route(Method::GET, "blobs.example", "/get/once", |_| {
+            response(StatusCode::FORBIDDEN, "Already used")
+        });
+        let session = session(transport);
+        let mut output = Vec::new();
+        let err = session
+            .fetch_blob_to(&node_id(1), &mut output, &mut |_| {})
+            .await
+            .unwrap_err();
+        match err {
+            Error::BlobUrlConsumed { api, status, .. } => {
+                assert_eq!(api, "GetBlob");
+                assert_eq!(status, StatusCode::FORBIDDEN);
+            }
+            other => panic!("Unexpected error: {:?}", other),
+        }
+        // Each attempt asked for a fresh URL
+        assert_eq!(session.client().count(DOCS), 3);
+    }
+
+    #[tokio::test]
+    async fn unverifiable_tokens_are_not_used() {
+        let verifier = crate::jwt::TokenVerifier::new().with_hmac_secret(b"secret");
+        let session = session(fake_cloud()).with_token_verifier(verifier);
+        match session.list().await {
+            Err(Error::Token(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert_eq!(session.client().count(USER_TOKEN), 0);
+    }
+
+    #[tokio::test]
+    async fn updates_are_not_retried() {
+        let transport = fake_cloud();
+        transport.route(Method::PUT, STORAGE_HOST, UPDATE_STATUS, |_| {
+            response(StatusCode::SERVICE_UNAVAILABLE, "")
+        });
+        let session = session(transport);
+        let err = session.update(&node()).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(session.client().count(UPDATE_STATUS), 1);
+    }
+
+    #[tokio::test]
+    async fn rename_keeps_other_metadata() {
+        let transport = fake_cloud();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, |_| {
+            json(&[DocsResponse::new(
+                true,
+                "",
+                node_id(1),
+                3,
+                "",
+                Timestamp::zero(),
+                "2019-09-01T10:29:26.440Z".parse().unwrap(),
+                NodeType::DocumentType,
+                "My Shiny Node",
+                7,
+                true,
+                ParentRef::Node(node_id(2)),
+            )])
+        });
+        transport.route(Method::PUT, STORAGE_HOST, UPDATE_STATUS, |req| {
+            let nodes: Vec<UpdateStatusRequest> = serde_json::from_slice(req.body()).unwrap();
+            json(&[UpdateStatusResponse::new(
+                true,
+                "",
+                Some(nodes[0].id().clone()),
+                nodes[0].version(),
+            )])
+        });
+        let session = session(transport);
+        assert_eq!(
+            session.rename_node(&node_id(1), "Renamed").await.unwrap(),
+            4
+        );
+        let sent = session
+            .client()
+            .requests()
+            .into_iter()
+            .find(|req| req.uri().path() == UPDATE_STATUS)
+            .unwrap();
+        let nodes: Vec<UpdateStatusRequest> = serde_json::from_slice(sent.body()).unwrap();
+        assert_eq!(nodes[0].name(), "Renamed");
+        assert_eq!(nodes[0].version(), 4);
+        assert_eq!(*nodes[0].parent(), node_id(2));
+        assert_eq!(nodes[0].current_page(), 7);
+        assert!(nodes[0].bookmarked());
+        assert_eq!(nodes[0].modified_client(), "2019-09-01T10:29:26.440Z");
+    }
+
+    #[tokio::test]
+    async fn fetch_doc_does_not_mint_blob_url() {
+        let transport = fake_cloud();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, |req| {
+            assert!(!req.uri().query().unwrap().contains("withBlob"));
+            let wanted = format!("doc={}", node_id(1).as_str().replace('-', "%2D"));
+            if req.uri().query() == Some(&*wanted) {
+                json(&[DocsResponse::new(
+                    true,
+                    "",
+                    node_id(1),
+                    3,
+                    "",
+                    Timestamp::zero(),
+                    "2019-09-01T10:29:26.440Z".parse().unwrap(),
+                    NodeType::DocumentType,
+                    "My Shiny Node",
+                    0,
+                    false,
+                    ParentRef::Root,
+                )])
+            } else {
+                json(&Vec::<DocsResponse>::new())
+            }
+        });
+        let session = session(transport);
+        let doc = session.fetch_doc(&node_id(1)).await.unwrap().unwrap();
+        assert_eq!(doc.version(), 3);
+        assert!(session.fetch_doc(&node_id(2)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn unstated_conflict_version_is_fetched() {
+        let transport = fake_cloud();
+        transport.route(Method::GET, STORAGE_HOST, DOCS, |_| json(&[blob_doc("")]));
+        let session = session(transport);
+        let conflict = |server_version| Error::VersionConflict {
+            api: "UpdateStatus",
+            message: "Version mismatch".to_owned(),
+            id: Some(node_id(1)),
+            server_version,
+        };
+        let id = node_id(1);
+        let stated = session.conflict_version(&id, &conflict(Some(5))).await;
+        assert_eq!(stated.unwrap(), Some(5));
+        assert_eq!(session.client().count(DOCS), 0);
+        let fetched = session.conflict_version(&id, &conflict(None)).await;
+        assert_eq!(fetched.unwrap(), Some(3));
+        assert_eq!(session.client().count(DOCS), 1);
+    }
+}
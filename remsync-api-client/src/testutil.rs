@@ -0,0 +1,94 @@
+//! Helpers shared by the tests in this crate
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use hyper::{Method, StatusCode, Uri};
+use remsync_api_types::*;
+
+use crate::memory::{json, response, MemoryTransport};
+use crate::mint::{SigningKey, TokenIssuer};
+use crate::{Backoff, RetryPolicy, StorageSession, Transport};
+
+/// The profile of the user in the tokens minted by [`user_token`]
+pub fn user_profile() -> Auth0Profile {
+    Auth0Profile::new(
+        "auth0|5d6b9d86fe3c560e1e4da801",
+        "someone@example.com",
+        "2019-09-01T10:29:26.440Z".parse().unwrap(),
+    )
+    .with_email_verified(true)
+    .with_name("someone@example.com")
+    .with_nickname("someone")
+    .with_updated_at("2019-09-01T10:31:21.678Z".parse().unwrap())
+}
+
+/// How long the tokens minted by [`user_token`] last
+const USER_TOKEN_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Mint a user token, as the auth service would, expiring at `expires_at`
+pub fn user_token(expires_at: u64) -> String {
+    let claims = UserToken::new(
+        user_profile(),
+        "desktop-linux",
+        "c81d2351-4723-4cfc-93ac-b032d3a053e7",
+        expires_at - USER_TOKEN_LIFETIME.as_secs(),
+        USER_TOKEN_LIFETIME,
+        "rM WebApp",
+        "ck0tCMsf9jQ=",
+    );
+    TokenIssuer::new(SigningKey::hmac(b"secret"))
+        .sign(&claims)
+        .expect("Unable to encode")
+}
+
+/// A node ID which differs for each `n`
+pub fn node_id(n: u32) -> NodeId {
+    format!("00000000-0000-4000-8000-{:012}", n)
+        .parse()
+        .unwrap()
+}
+
+pub const AUTH_HOST: &str = "auth.example";
+pub const DISCOVERY_HOST: &str = "discovery.example";
+pub const STORAGE_HOST: &str = "storage.example";
+
+/// A transport answering as the auth and discovery services would
+///
+/// Storage routes are left to each test.
+pub fn fake_cloud() -> MemoryTransport {
+    let transport = MemoryTransport::new();
+    transport.route(Method::POST, AUTH_HOST, "/token/json/2/user/new", |_| {
+        response(StatusCode::OK, user_token(now_epoch_secs() + 3600))
+    });
+    transport.route(
+        Method::GET,
+        DISCOVERY_HOST,
+        "/service/json/1/document-storage",
+        |_| json(&DiscoveryResponse::new("OK", STORAGE_HOST)),
+    );
+    transport
+}
+
+/// A session on top of the given transport which retries quickly
+pub fn session<T: Transport>(transport: T) -> StorageSession<T> {
+    let auth: Uri = format!("https://{}/", AUTH_HOST).parse().unwrap();
+    let discovery: Uri = format!("https://{}/", DISCOVERY_HOST).parse().unwrap();
+    StorageSession::new(transport, auth, discovery, "some-device-token").with_retry_policy(
+        RetryPolicy::new(
+            3,
+            Backoff::new(Duration::from_millis(1), Duration::from_millis(1)),
+        ),
+    )
+}
+
+/// A counter for scripting a sequence of responses from a handler
+#[derive(Default)]
+pub struct Calls(AtomicUsize);
+
+impl Calls {
+    /// Count a call, returning its number from 1
+    pub fn next(&self) -> usize {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
@@ -0,0 +1,193 @@
+//! Caching of user tokens
+//!
+//! User tokens are valid for around a day, so there's no need to acquire a
+//! fresh one on every run.  A [`TokenCache`] remembers user tokens keyed by
+//! the device token they were acquired with, and optionally persists them
+//! to a file so that they survive between runs.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use remsync_api_types::{now_epoch_secs, UserToken};
+use sha2::{Digest, Sha256};
+
+use crate::Result;
+
+/// How close to expiry a cached token may get before we stop using it
+const EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// A cache of user tokens, keyed by device token
+///
+/// The cache is always held in memory, and if it was created with
+/// [`TokenCache::on_disk`] then every change is also written out to disk.
+/// Device tokens are never stored, only a hash of them.
+#[derive(Debug, Default)]
+pub struct TokenCache {
+    path: Option<PathBuf>,
+    tokens: Mutex<HashMap<String, String>>,
+}
+
+impl TokenCache {
+    /// Create a cache which only lives in memory
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache which persists to the given file
+    ///
+    /// If the file exists then any tokens in it are loaded.  Tokens which
+    /// have already expired are discarded at load time.
+    pub fn on_disk<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        let tokens: HashMap<String, String> = match fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let tokens = tokens
+            .into_iter()
+            .filter(|(_, token)| token_is_fresh(token, now_epoch_secs()))
+            .collect();
+        Ok(Self {
+            path: Some(path),
+            tokens: Mutex::new(tokens),
+        })
+    }
+
+    /// The file this cache persists to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Retrieve a usable user token for the given device token
+    ///
+    /// Tokens which are within five minutes of expiring are not returned,
+    /// so that callers don't start work with a token which will expire
+    /// partway through.
+    pub fn get(&self, device_token: &str) -> Option<String> {
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .get(&cache_key(device_token))
+            .filter(|token| token_is_fresh(token, now_epoch_secs()))
+            .cloned()
+    }
+
+    /// Remember a user token for the given device token
+    pub fn insert(&self, device_token: &str, user_token: &str) -> Result<()> {
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.insert(cache_key(device_token), user_token.to_owned());
+        self.save(&tokens)
+    }
+
+    /// Forget any user token for the given device token
+    ///
+    /// This is used when the server rejects a token which we thought was
+    /// still valid.
+    pub fn invalidate(&self, device_token: &str) -> Result<()> {
+        let mut tokens = self.tokens.lock().unwrap();
+        if tokens.remove(&cache_key(device_token)).is_some() {
+            self.save(&tokens)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, tokens: &HashMap<String, String>) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file and rename it into place so that a
+        // concurrent reader never sees a partial file.
+        let mut temp = path.clone();
+        temp.set_extension("tmp");
+        {
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let file = options.open(&temp)?;
+            serde_json::to_writer_pretty(file, tokens)?;
+        }
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// Whether a user token is decodable and not about to expire
+fn token_is_fresh(token: &str, now: u64) -> bool {
+    match jsonwebtoken::dangerous_unsafe_decode::<UserToken>(token) {
+        Ok(token) => !token.claims.is_expired(now + EXPIRY_MARGIN.as_secs()),
+        Err(_) => false,
+    }
+}
+
+/// The key for a device token in the cache
+fn cache_key(device_token: &str) -> String {
+    Sha256::digest(device_token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil::user_token;
+
+    #[test]
+    fn fresh_tokens_are_returned() {
+        let cache = TokenCache::in_memory();
+        let token = user_token(now_epoch_secs() + 3600);
+        cache.insert("device", &token).unwrap();
+        assert_eq!(cache.get("device"), Some(token));
+        assert_eq!(cache.get("other-device"), None);
+    }
+
+    #[test]
+    fn nearly_expired_tokens_are_not_returned() {
+        let cache = TokenCache::in_memory();
+        cache
+            .insert("device", &user_token(now_epoch_secs() + 60))
+            .unwrap();
+        assert_eq!(cache.get("device"), None);
+    }
+
+    #[test]
+    fn invalidated_tokens_are_not_returned() {
+        let cache = TokenCache::in_memory();
+        cache
+            .insert("device", &user_token(now_epoch_secs() + 3600))
+            .unwrap();
+        cache.invalidate("device").unwrap();
+        assert_eq!(cache.get("device"), None);
+    }
+
+    #[test]
+    fn tokens_persist_on_disk() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("remsync-token-cache-{}", std::process::id()));
+        path.push("tokens.json");
+        let token = user_token(now_epoch_secs() + 3600);
+        {
+            let cache = TokenCache::on_disk(&path).unwrap();
+            cache.insert("device", &token).unwrap();
+            cache
+                .insert("expiring", &user_token(now_epoch_secs()))
+                .unwrap();
+        }
+        let cache = TokenCache::on_disk(&path).unwrap();
+        assert_eq!(cache.get("device"), Some(token));
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("\"device\""));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+//! Running many blob transfers at once
+//!
+//! Fetching or putting blobs one at a time leaves the connection idle for
+//! much of each round trip.  A [`TransferScheduler`] runs a batch of
+//! [`Transfer`]s with a bounded number in flight at once, holding back any
+//! which must wait for another to finish first (for example a document
+//! whose parent collection is being uploaded in the same batch), and
+//! gathers the results into [`TransferResults`].
+//!
+//! The transfers all run within the calling task, so they may freely
+//! borrow from it, a [`StorageSession`](crate::StorageSession) included.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use remsync_api_types::NodeId;
+
+use crate::{Error, Result};
+
+/// A single transfer in a batch, identified by the ID of its node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    id: NodeId,
+    after: Option<NodeId>,
+}
+
+impl Transfer {
+    /// A transfer for the node with the given ID
+    ///
+    /// ```
+    /// # use remsync_api_client::Transfer;
+    /// let transfer = Transfer::new("092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap());
+    /// assert_eq!(transfer.id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
+    /// ```
+    pub fn new(id: NodeId) -> Self {
+        Self { id, after: None }
+    }
+
+    /// Only start this transfer once the transfer for node `id` has
+    /// succeeded, if there is one in the same batch
+    ///
+    /// If that transfer fails, this one is not attempted.
+    ///
+    /// ```
+    /// # use remsync_api_client::Transfer;
+    /// # use remsync_api_types::NodeId;
+    /// let node: NodeId = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// let parent: NodeId = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+    /// let transfer = Transfer::new(node).after(parent.clone());
+    /// assert_eq!(transfer.waits_for(), Some(&parent));
+    /// ```
+    pub fn after(mut self, id: NodeId) -> Self {
+        self.after = Some(id);
+        self
+    }
+
+    /// The ID of the node being transferred
+    pub fn id(&self) -> &NodeId {
+        &self.id
+    }
+
+    /// The ID of the node whose transfer must succeed first, if any
+    pub fn waits_for(&self) -> Option<&NodeId> {
+        self.after.as_ref()
+    }
+}
+
+/// The outcome of every transfer in a batch, in the order the transfers
+/// were given
+#[derive(Debug)]
+pub struct TransferResults<T> {
+    results: Vec<(NodeId, Result<T>)>,
+}
+
+impl<T> TransferResults<T> {
+    /// Each node ID with the result of its transfer
+    pub fn iter(&self) -> impl Iterator<Item = (&NodeId, &Result<T>)> {
+        self.results.iter().map(|(id, ret)| (id, ret))
+    }
+
+    /// How many transfers succeeded
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|(_, ret)| ret.is_ok()).count()
+    }
+
+    /// How many transfers failed or were not attempted
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.succeeded()
+    }
+
+    /// The transfers which failed, with their errors
+    pub fn failures(&self) -> impl Iterator<Item = (&NodeId, &Error)> {
+        self.results.iter().filter_map(|(id, ret)| match ret {
+            Ok(_) => None,
+            Err(e) => Some((id, e)),
+        })
+    }
+}
+
+impl<T> IntoIterator for TransferResults<T> {
+    type Item = (NodeId, Result<T>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.into_iter()
+    }
+}
+
+/// Runs batches of transfers with a bounded number in flight
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransferScheduler {
+    jobs: usize,
+}
+
+/// Whether a waiting transfer can start yet
+enum Readiness {
+    Ready,
+    Waiting,
+    Blocked(NodeId),
+}
+
+impl TransferScheduler {
+    /// A scheduler running at most `jobs` transfers at once
+    ///
+    /// ```
+    /// # use remsync_api_client::TransferScheduler;
+    /// assert_eq!(TransferScheduler::new(4).jobs(), 4);
+    /// assert_eq!(TransferScheduler::new(0).jobs(), 1);
+    /// ```
+    pub fn new(jobs: usize) -> Self {
+        Self { jobs: jobs.max(1) }
+    }
+
+    /// The most transfers which will be in flight at once
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Run `op` for each of `transfers`, respecting their ordering
+    ///
+    /// Transfers start in the order given, as soon as there's room and
+    /// whatever they wait for has succeeded.  A transfer which waits for
+    /// one which fails, or which is caught in a cycle of waits, is not
+    /// attempted and reports [`Error::DependencyFailed`].
+    pub async fn run<'a, F, Fut, T>(
+        &self,
+        transfers: &'a [Transfer],
+        mut op: F,
+    ) -> TransferResults<T>
+    where
+        F: FnMut(&'a Transfer) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let index: HashMap<&NodeId, usize> = transfers
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(n, transfer)| (transfer.id(), n))
+            .collect();
+        let mut results: Vec<Option<Result<T>>> = transfers.iter().map(|_| None).collect();
+        let mut waiting: Vec<usize> = (0..transfers.len()).collect();
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            // Start everything we can.  Blocking one transfer may block
+            // another earlier in the queue, so go round until nothing moves
+            let mut moved = true;
+            while moved {
+                moved = false;
+                let mut i = 0;
+                while i < waiting.len() {
+                    let n = waiting[i];
+                    let readiness = match transfers[n].waits_for().and_then(|id| index.get(id)) {
+                        Some(&dep) if dep != n => match &results[dep] {
+                            None => Readiness::Waiting,
+                            Some(Ok(_)) => Readiness::Ready,
+                            Some(Err(_)) => Readiness::Blocked(transfers[dep].id.clone()),
+                        },
+                        _ => Readiness::Ready,
+                    };
+                    match readiness {
+                        Readiness::Ready if running.len() < self.jobs => {
+                            let transfer = op(&transfers[n]);
+                            running.push(async move { (n, transfer.await) });
+                        }
+                        Readiness::Blocked(dependency) => {
+                            results[n] = Some(Err(Error::DependencyFailed {
+                                id: transfers[n].id.clone(),
+                                dependency,
+                            }));
+                        }
+                        _ => {
+                            i += 1;
+                            continue;
+                        }
+                    }
+                    waiting.remove(i);
+                    moved = true;
+                }
+            }
+
+            match running.next().await {
+                Some((n, ret)) => results[n] = Some(ret),
+                None => break,
+            }
+        }
+
+        // Anything still waiting is stuck in a cycle
+        for n in waiting {
+            results[n] = Some(Err(Error::DependencyFailed {
+                id: transfers[n].id.clone(),
+                dependency: transfers[n]
+                    .after
+                    .clone()
+                    .expect("Waiting without a dependency"),
+            }));
+        }
+
+        TransferResults {
+            results: transfers
+                .iter()
+                .zip(results)
+                .map(|(transfer, ret)| (transfer.id.clone(), ret.expect("Transfer not run")))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::time::{Duration, Instant};
+    use tokio::timer::delay;
+
+    async fn pause() {
+        delay(Instant::now() + Duration::from_millis(5)).await;
+    }
+
+    fn id(name: &str) -> NodeId {
+        NodeId::parse_lenient(name).unwrap()
+    }
+
+    #[tokio::test]
+    async fn transfers_are_bounded() {
+        let transfers: Vec<Transfer> = (0..10).map(|n| Transfer::new(id(&n.to_string()))).collect();
+        let active = Cell::new(0);
+        let most = Cell::new(0);
+        let results = TransferScheduler::new(3)
+            .run(&transfers, |transfer| {
+                let (active, most) = (&active, &most);
+                async move {
+                    active.set(active.get() + 1);
+                    most.set(most.get().max(active.get()));
+                    pause().await;
+                    active.set(active.get() - 1);
+                    Ok(transfer.id().as_str().len())
+                }
+            })
+            .await;
+        assert_eq!(most.get(), 3);
+        assert_eq!(results.succeeded(), 10);
+        let ids: Vec<NodeId> = results.into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, (0..10).map(|n| id(&n.to_string())).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn children_wait_for_parents() {
+        let transfers = vec![
+            Transfer::new(id("child")).after(id("parent")),
+            Transfer::new(id("orphan")).after(id("elsewhere")),
+            Transfer::new(id("parent")),
+            Transfer::new(id("doomed")).after(id("broken")),
+            Transfer::new(id("broken")),
+        ];
+        let order = RefCell::new(Vec::new());
+        let results = TransferScheduler::new(4)
+            .run(&transfers, |transfer| {
+                let order = &order;
+                async move {
+                    pause().await;
+                    order.borrow_mut().push(transfer.id().as_str());
+                    if transfer.id() == "broken" {
+                        Err(Error::protocol("UploadRequestBlobPut", "oops"))
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .await;
+        let order = order.into_inner();
+        let position = |id| order.iter().position(|done| *done == id).unwrap();
+        assert!(position("parent") < position("child"));
+        assert!(!order.contains(&"doomed"));
+        assert_eq!(results.succeeded(), 3);
+        let failed: Vec<&str> = results.failures().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(failed, vec!["doomed", "broken"]);
+    }
+
+    #[tokio::test]
+    async fn cycles_are_not_attempted() {
+        let transfers = vec![
+            Transfer::new(id("a")).after(id("b")),
+            Transfer::new(id("b")).after(id("a")),
+        ];
+        let results = TransferScheduler::new(2)
+            .run(&transfers, |_| async { Ok(()) })
+            .await;
+        assert_eq!(results.failed(), 2);
+    }
+}
@@ -0,0 +1,44 @@
+//! The HTTP transport used to reach the cloud services
+//!
+//! Every function in [`ll`](crate::ll) sends its requests through a
+//! [`Transport`].  Normally that is a hyper [`Client`], but anything which
+//! can turn a request into a response will do, which lets tests run the
+//! client against scripted servers without touching the network.  See
+//! [`memory`](crate::memory) for one such transport.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use hyper::client::connect::Connect;
+use hyper::{Body, Client, Request, Response};
+
+use crate::Result;
+
+/// The future returned by [`Transport::request`]
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<Response<Body>>> + Send + 'a>>;
+
+/// Something which can carry HTTP requests to a server and bring back the
+/// responses
+pub trait Transport: Send + Sync {
+    /// Send `request` and wait for the head of the response
+    fn request(&self, request: Request<Body>) -> TransportFuture<'_>;
+}
+
+impl<C> Transport for Client<C, Body>
+where
+    C: Connect + Sync + 'static,
+{
+    fn request(&self, request: Request<Body>) -> TransportFuture<'_> {
+        let response = Client::request(self, request);
+        Box::pin(async move { Ok(response.await?) })
+    }
+}
+
+impl<T> Transport for Box<T>
+where
+    T: Transport + ?Sized,
+{
+    fn request(&self, request: Request<Body>) -> TransportFuture<'_> {
+        (**self).request(request)
+    }
+}
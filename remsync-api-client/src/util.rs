@@ -1,11 +1,14 @@
 //! Utility functions for internal use only.
 
 use hyper::Body;
+use hyper::Response;
+use hyper::StatusCode;
 use hyper::Uri;
+use remsync_api_types::*;
 
-use crate::GenericResult;
+use crate::{BlobUrl, Error, Result};
 
-pub fn catenate_url_path(base: &Uri, path: &str) -> GenericResult<Uri> {
+pub fn catenate_url_path(base: &Uri, path: &str) -> Result<Uri> {
     let mut builder = Uri::builder();
 
     // Copy the scheme
@@ -20,7 +23,7 @@ pub fn catenate_url_path(base: &Uri, path: &str) -> GenericResult<Uri> {
     // But catenate the path
     let mut base_path = base.path();
 
-    if base_path.ends_with("/") {
+    if base_path.ends_with('/') {
         base_path = &base_path[..base_path.len() - 1];
     }
 
@@ -29,7 +32,7 @@ pub fn catenate_url_path(base: &Uri, path: &str) -> GenericResult<Uri> {
     Ok(builder.build()?)
 }
 
-pub async fn hoover_body_to_vec(mut body: Body) -> GenericResult<Vec<u8>> {
+pub async fn hoover_body_to_vec(mut body: Body) -> Result<Vec<u8>> {
     let mut ret = Vec::new();
     while let Some(next) = body.next().await {
         let chunk = next?;
@@ -37,3 +40,376 @@ pub async fn hoover_body_to_vec(mut body: Body) -> GenericResult<Vec<u8>> {
     }
     Ok(ret)
 }
+
+/// Turn a non-success response into an `Error::Status`, consuming the body
+pub async fn check_status(api: &'static str, response: Response<Body>) -> Result<Response<Body>> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    // If we can't read the body, we still want to report the status
+    let body = hoover_body_to_vec(response.into_body())
+        .await
+        .unwrap_or_default();
+    Err(Error::Status {
+        api,
+        status,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+/// Like [`check_status`] for a request to a signed blob URL
+///
+/// The blob store rejects URLs it won't honour with a 4xx status.  If the
+/// URL had expired by then we report that, otherwise it must have been used
+/// already.  Either way the caller needs a fresh URL.
+pub async fn check_blob_status(
+    api: &'static str,
+    url: &BlobUrl,
+    response: Response<Body>,
+) -> Result<Response<Body>> {
+    match check_status(api, response).await {
+        Err(Error::Status { status, body, .. })
+            if status == StatusCode::BAD_REQUEST
+                || status == StatusCode::UNAUTHORIZED
+                || status == StatusCode::FORBIDDEN
+                || status == StatusCode::GONE =>
+        {
+            if url.is_expired() {
+                Err(Error::BlobUrlExpired { api })
+            } else {
+                Err(Error::BlobUrlConsumed { api, status, body })
+            }
+        }
+        other => other,
+    }
+}
+
+/// The common fields of the storage API's per-node replies
+pub trait ServerReply {
+    fn success(&self) -> bool;
+    fn message(&self) -> &str;
+    fn id(&self) -> Option<&NodeId>;
+    fn version(&self) -> usize;
+}
+
+macro_rules! server_reply {
+    ($($t:ty),*) => {
+        $(
+            impl ServerReply for $t {
+                fn success(&self) -> bool {
+                    <$t>::success(self)
+                }
+                fn message(&self) -> &str {
+                    <$t>::message(self)
+                }
+                fn id(&self) -> Option<&NodeId> {
+                    <$t>::id(self).into()
+                }
+                fn version(&self) -> usize {
+                    <$t>::version(self)
+                }
+            }
+        )*
+    };
+}
+
+server_reply!(
+    DocsResponse,
+    UploadRequestResponse,
+    UpdateStatusResponse,
+    DeleteResponse
+);
+
+/// The (lower case) failure messages the storage service uses for a
+/// version mismatch which don't spell out the versions
+///
+/// Each phrase has a reply in the `CONFLICT_REPLIES` test fixture below,
+/// which is what it's matched against.
+const CONFLICT_MESSAGES: &[&str] = &["version mismatch", "wrong version"];
+
+/// Whether a failure message reports a version mismatch
+///
+/// Plenty of other failures mention a version, so only the service's own
+/// phrasings count.
+fn is_version_conflict(message: &str) -> bool {
+    let lower = message.to_ascii_lowercase();
+    CONFLICT_MESSAGES.iter().any(|known| lower.contains(known))
+        || stated_server_version(&lower).is_some()
+}
+
+/// The server's version from a message which spells out the versions, as
+/// in `Server: 3 vs Your: 5`
+///
+/// The full reply is in the `CONFLICT_REPLIES` test fixture below.
+fn stated_server_version(lower: &str) -> Option<usize> {
+    let start = lower.find("server:")?;
+    let rest = lower[start + "server:".len()..].trim_start();
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let version = rest[..end].parse().ok()?;
+    let rest = rest[end..].trim_start().strip_prefix("vs")?;
+    if rest.trim_start().starts_with("your:") {
+        Some(version)
+    } else {
+        None
+    }
+}
+
+/// The server's version of a node, from a version conflict reply
+///
+/// Only a message which spells out the versions (`Server: 3 vs Your: 5`)
+/// says what the server holds.  The reply's own `Version` may just echo
+/// the one sent, so it's no use here.
+fn conflict_version(message: &str) -> Option<usize> {
+    stated_server_version(&message.to_ascii_lowercase())
+}
+
+/// The ID in a reply, falling back to the one requested
+fn reply_id<R: ServerReply>(reply: &R, requested: Option<&NodeId>) -> Option<NodeId> {
+    reply.id().or(requested).cloned()
+}
+
+/// Turn a `Success: false` reply into an `Error::VersionConflict` or an
+/// `Error::Server`
+///
+/// Failures may come back without an ID, in which case the error carries
+/// the `requested` one, if known
+pub fn check_reply<R: ServerReply>(
+    api: &'static str,
+    requested: Option<&NodeId>,
+    reply: R,
+) -> Result<R> {
+    if reply.success() {
+        Ok(reply)
+    } else if is_version_conflict(reply.message()) {
+        Err(Error::VersionConflict {
+            api,
+            message: reply.message().to_owned(),
+            id: reply_id(&reply, requested),
+            server_version: conflict_version(reply.message()),
+        })
+    } else {
+        Err(Error::Server {
+            api,
+            message: reply.message().to_owned(),
+            id: reply_id(&reply, requested),
+            version: reply.version(),
+        })
+    }
+}
+
+/// Extract the sole reply from a list which ought to contain exactly one
+pub fn single_reply<R: ServerReply>(api: &'static str, mut replies: Vec<R>) -> Result<R> {
+    if replies.len() != 1 {
+        return Err(Error::protocol(
+            api,
+            format!("Expected 1 response, got {}", replies.len()),
+        ));
+    }
+    check_reply(api, None, replies.remove(0))
+}
+
+/// Match up the replies to a batch request with the IDs which were sent
+///
+/// The server replies to each entry in turn, so every reply is checked on
+/// its own and a failed entry doesn't hide the outcome of the others.  Only
+/// a reply list of the wrong length fails the batch as a whole.
+pub fn batch_replies<R: ServerReply>(
+    api: &'static str,
+    ids: &[&NodeId],
+    replies: Vec<R>,
+) -> Result<Vec<Result<R>>> {
+    if replies.len() != ids.len() {
+        return Err(Error::protocol(
+            api,
+            format!("Expected {} responses, got {}", ids.len(), replies.len()),
+        ));
+    }
+    Ok(ids
+        .iter()
+        .zip(replies)
+        .map(|(id, reply)| {
+            // Failures may come back without an ID, so only complain about
+            // a mismatch if there's an ID to compare
+            if let Some(got) = reply.id().filter(|got| got != id) {
+                return Err(Error::protocol(
+                    api,
+                    format!("Expected response for {}, got {}", id, got),
+                ));
+            }
+            check_reply(api, Some(id), reply)
+        })
+        .collect())
+}
+
+/// Extract the sole result from the replies to a batch of one
+pub fn single_result<R>(mut results: Vec<Result<R>>) -> Result<R> {
+    debug_assert_eq!(results.len(), 1);
+    results.remove(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil::node_id;
+
+    #[test]
+    fn catenate() {
+        let base: Uri = "https://example.com/base/".parse().unwrap();
+        let uri = catenate_url_path(&base, "/some/path?q=1").unwrap();
+        assert_eq!(uri, "https://example.com/base/some/path?q=1");
+    }
+
+    #[test]
+    fn failed_reply_is_server_error() {
+        let reply = DeleteResponse::new(false, "No such document", Some(node_id(1)), 4);
+        match single_reply("DeleteDoc", vec![reply]) {
+            Err(Error::Server {
+                api,
+                message,
+                id,
+                version,
+            }) => {
+                assert_eq!(api, "DeleteDoc");
+                assert_eq!(message, "No such document");
+                assert_eq!(id, Some(node_id(1)));
+                assert_eq!(version, 4);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    /// The conflict replies which [`CONFLICT_MESSAGES`] and
+    /// [`stated_server_version`] are written against, with the server
+    /// version each one states
+    const CONFLICT_REPLIES: &[(&str, &str, Option<usize>)] = &[
+        (
+            "DeleteDoc",
+            r#"[{
+                "ID": "092fd1cc-df38-4fc5-8633-3a8a15a2a316",
+                "Version": 4,
+                "Message": "Version mismatch",
+                "Success": false
+            }]"#,
+            None,
+        ),
+        (
+            "UploadRequest",
+            r#"[{
+                "ID": "092fd1cc-df38-4fc5-8633-3a8a15a2a316",
+                "Version": 5,
+                "Message": "Version on server is not -1 of what you supplied: Server: 3 vs Your: 5",
+                "Success": false,
+                "BlobURLPut": "",
+                "BlobURLPutExpires": "0001-01-01T00:00:00Z"
+            }]"#,
+            Some(3),
+        ),
+        (
+            "UpdateStatus",
+            r#"[{
+                "ID": "",
+                "Version": 2,
+                "Message": "Wrong version",
+                "Success": false
+            }]"#,
+            None,
+        ),
+    ];
+
+    /// The error for a fixture reply to `api`
+    fn conflict<R>(api: &'static str, body: &str) -> Error
+    where
+        R: ServerReply + serde::de::DeserializeOwned + std::fmt::Debug,
+    {
+        let replies: Vec<R> = serde_json::from_str(body).unwrap();
+        single_reply(api, replies).unwrap_err()
+    }
+
+    #[test]
+    fn version_mismatch_is_conflict() {
+        for (api, body, server_version) in CONFLICT_REPLIES {
+            let err = match *api {
+                "DeleteDoc" => conflict::<DeleteResponse>(api, body),
+                "UploadRequest" => conflict::<UploadRequestResponse>(api, body),
+                _ => conflict::<UpdateStatusResponse>(api, body),
+            };
+            assert!(err.is_version_conflict(), "{}: {:?}", api, err);
+            assert_eq!(err.conflict_version(), *server_version, "{}", api);
+        }
+        let reply = UploadRequestResponse::new(
+            false,
+            "Version on server is not -1 of what you supplied: Server: 3 vs Your: 5",
+            Some(node_id(1)),
+            5,
+            "",
+            Timestamp::zero(),
+        );
+        match single_reply("UploadRequest", vec![reply]) {
+            Err(Error::VersionConflict {
+                id, server_version, ..
+            }) => {
+                assert_eq!(id, Some(node_id(1)));
+                assert_eq!(server_version, Some(3));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn other_version_failures_are_not_conflicts() {
+        for message in &[
+            "Unsupported API version",
+            "Firmware version 1.7 is too old",
+            "Server: unavailable, try a newer version",
+        ] {
+            let reply = UpdateStatusResponse::new(false, message, Some(node_id(1)), 2);
+            match single_reply("UpdateStatus", vec![reply]) {
+                Err(Error::Server { message: got, .. }) => assert_eq!(got, *message),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn batch_failures_are_reported_per_item() {
+        let replies = vec![
+            DeleteResponse::new(true, "", Some(node_id(1)), 2),
+            DeleteResponse::new(false, "No such document", None, 3),
+            DeleteResponse::new(true, "", Some(node_id(4)), 1),
+        ];
+        let ids = [node_id(1), node_id(2), node_id(3)];
+        let ids: Vec<&NodeId> = ids.iter().collect();
+        let results = batch_replies("DeleteDoc", &ids, replies).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().id(), Some(&node_id(1)));
+        match &results[1] {
+            Err(Error::Server { id, .. }) => assert_eq!(id.as_ref(), Some(&node_id(2))),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match &results[2] {
+            Err(Error::Protocol { .. }) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn batch_reply_count_must_match() {
+        let replies = vec![DeleteResponse::new(true, "", Some(node_id(1)), 2)];
+        match batch_replies("DeleteDoc", &[&node_id(1), &node_id(2)], replies) {
+            Err(Error::Protocol { api, .. }) => assert_eq!(api, "DeleteDoc"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wrong_reply_count_is_protocol_error() {
+        let replies: Vec<DeleteResponse> = Vec::new();
+        match single_reply("DeleteDoc", replies) {
+            Err(Error::Protocol { api, .. }) => assert_eq!(api, "DeleteDoc"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! A minimal websocket framing codec
+//!
+//! The notification service speaks websockets (RFC 6455).  We only need a
+//! small part of that protocol: text, binary, ping, pong and close frames,
+//! with fragmented messages reassembled.  No extensions are negotiated, so
+//! the reserved bits must always be clear.
+
+use bytes::{BufMut, BytesMut};
+use sha1::{Digest, Sha1};
+use tokio::codec::{Decoder, Encoder};
+
+use crate::{Error, Result};
+
+/// The API name used in errors from the websocket layer
+pub const API: &str = "Notifications";
+
+/// The GUID which is appended to the key during the opening handshake
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest message we're prepared to reassemble
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// The close status code for a normal closure
+pub const CLOSE_NORMAL: u16 = 1000;
+
+/// A complete websocket message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The close payload, which is either empty or a status code
+    /// followed by a UTF-8 reason
+    Close(Vec<u8>),
+}
+
+impl Message {
+    /// A close message with the given status code and no reason
+    pub fn close(code: u16) -> Self {
+        Message::Close(code.to_be_bytes().to_vec())
+    }
+}
+
+/// Which end of the connection we are
+///
+/// Clients must mask every frame they send, servers must never mask.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// A codec turning a byte stream into websocket messages and back
+#[derive(Debug)]
+pub struct WebSocketCodec {
+    role: Role,
+    /// The opcode and payload so far of a fragmented message
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl WebSocketCodec {
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            partial: None,
+        }
+    }
+}
+
+/// A single frame, already unmasked
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+impl WebSocketCodec {
+    /// Parse one frame from the front of `src` if it is all there
+    fn decode_frame(&self, src: &mut BytesMut) -> Result<Option<Frame>> {
+        if src.len() < 2 {
+            return Ok(None);
+        }
+        let fin = src[0] & 0x80 != 0;
+        if src[0] & 0x70 != 0 {
+            return Err(Error::protocol(API, "Reserved bits set in frame"));
+        }
+        let opcode = src[0] & 0x0F;
+        let masked = src[1] & 0x80 != 0;
+        if masked != (self.role == Role::Server) {
+            return Err(Error::protocol(API, "Frame masking is wrong for our role"));
+        }
+
+        let (mut header_len, len) = match src[1] & 0x7F {
+            126 => {
+                if src.len() < 4 {
+                    return Ok(None);
+                }
+                (4, u64::from(u16::from_be_bytes([src[2], src[3]])))
+            }
+            127 => {
+                if src.len() < 10 {
+                    return Ok(None);
+                }
+                let mut len = [0u8; 8];
+                len.copy_from_slice(&src[2..10]);
+                (10, u64::from_be_bytes(len))
+            }
+            len => (2, u64::from(len)),
+        };
+        if len > MAX_MESSAGE_LEN as u64 {
+            return Err(Error::protocol(API, format!("Frame too large ({})", len)));
+        }
+        let len = len as usize;
+
+        let mut mask = None;
+        if masked {
+            if src.len() < header_len + 4 {
+                return Ok(None);
+            }
+            let mut key = [0u8; 4];
+            key.copy_from_slice(&src[header_len..header_len + 4]);
+            mask = Some(key);
+            header_len += 4;
+        }
+
+        if src.len() < header_len + len {
+            src.reserve(header_len + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(header_len);
+        let mut payload = src.split_to(len).to_vec();
+        if let Some(key) = mask {
+            apply_mask(&mut payload, key);
+        }
+
+        Ok(Some(Frame {
+            fin,
+            opcode,
+            payload,
+        }))
+    }
+}
+
+impl Decoder for WebSocketCodec {
+    type Item = Message;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Message>> {
+        while let Some(frame) = self.decode_frame(src)? {
+            match frame.opcode {
+                OP_CONTINUATION => {
+                    let (opcode, mut payload) = self.partial.take().ok_or_else(|| {
+                        Error::protocol(API, "Continuation frame without a message to continue")
+                    })?;
+                    if payload.len() + frame.payload.len() > MAX_MESSAGE_LEN {
+                        return Err(Error::protocol(API, "Fragmented message too large"));
+                    }
+                    payload.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return data_message(opcode, payload).map(Some);
+                    }
+                    self.partial = Some((opcode, payload));
+                }
+                OP_TEXT | OP_BINARY => {
+                    if self.partial.is_some() {
+                        return Err(Error::protocol(
+                            API,
+                            "New message started before the last one finished",
+                        ));
+                    }
+                    if frame.fin {
+                        return data_message(frame.opcode, frame.payload).map(Some);
+                    }
+                    self.partial = Some((frame.opcode, frame.payload));
+                }
+                OP_CLOSE | OP_PING | OP_PONG => {
+                    // Control frames may be interleaved with the fragments of
+                    // a message, but may not themselves be fragmented
+                    if !frame.fin || frame.payload.len() > 125 {
+                        return Err(Error::protocol(API, "Malformed control frame"));
+                    }
+                    return Ok(Some(match frame.opcode {
+                        OP_CLOSE => Message::Close(frame.payload),
+                        OP_PING => Message::Ping(frame.payload),
+                        _ => Message::Pong(frame.payload),
+                    }));
+                }
+                opcode => {
+                    return Err(Error::protocol(
+                        API,
+                        format!("Unknown frame opcode {:#x}", opcode),
+                    ))
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Encoder for WebSocketCodec {
+    type Item = Message;
+    type Error = Error;
+
+    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<()> {
+        let (opcode, mut payload) = match item {
+            Message::Text(text) => (OP_TEXT, text.into_bytes()),
+            Message::Binary(data) => (OP_BINARY, data),
+            Message::Ping(data) => (OP_PING, data),
+            Message::Pong(data) => (OP_PONG, data),
+            Message::Close(data) => (OP_CLOSE, data),
+        };
+
+        dst.reserve(14 + payload.len());
+        dst.put_u8(0x80 | opcode);
+        let mask_bit = if self.role == Role::Client { 0x80 } else { 0 };
+        match payload.len() {
+            len if len < 126 => dst.put_u8(mask_bit | len as u8),
+            len if len <= 0xFFFF => {
+                dst.put_u8(mask_bit | 126);
+                dst.put_u16_be(len as u16);
+            }
+            len => {
+                dst.put_u8(mask_bit | 127);
+                dst.put_u64_be(len as u64);
+            }
+        }
+        if self.role == Role::Client {
+            let key: [u8; 4] = rand::random();
+            dst.put_slice(&key);
+            apply_mask(&mut payload, key);
+        }
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+
+/// Build a text or binary message from its reassembled payload
+fn data_message(opcode: u8, payload: Vec<u8>) -> Result<Message> {
+    if opcode == OP_TEXT {
+        String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|_| Error::protocol(API, "Text message is not UTF-8"))
+    } else {
+        Ok(Message::Binary(payload))
+    }
+}
+
+/// Mask (or unmask, it's the same operation) a payload in place
+fn apply_mask(payload: &mut [u8], key: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= key[i % 4];
+    }
+}
+
+/// Generate a fresh `Sec-WebSocket-Key` for the opening handshake
+pub fn generate_key() -> String {
+    let key: [u8; 16] = rand::random();
+    base64::encode(&key)
+}
+
+/// Compute the `Sec-WebSocket-Accept` the server must reply with for `key`
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(ACCEPT_GUID.as_bytes());
+    base64::encode(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(message: Message) {
+        let mut client = WebSocketCodec::new(Role::Client);
+        let mut server = WebSocketCodec::new(Role::Server);
+        let mut buf = BytesMut::new();
+        client.encode(message.clone(), &mut buf).unwrap();
+        // Feed the frame in a byte at a time to check partial frames
+        let mut input = BytesMut::new();
+        let mut decoded = None;
+        for byte in buf.iter() {
+            assert!(decoded.is_none());
+            input.extend_from_slice(&[*byte]);
+            decoded = server.decode(&mut input).unwrap();
+        }
+        assert_eq!(decoded, Some(message));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn accept_key_matches_rfc() {
+        // The example from RFC 6455 section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        round_trip(Message::Text("Hello".to_owned()));
+        round_trip(Message::Binary(vec![7; 300]));
+        round_trip(Message::Binary(vec![9; 70000]));
+        round_trip(Message::Ping(b"ping".to_vec()));
+        round_trip(Message::close(CLOSE_NORMAL));
+    }
+
+    #[test]
+    fn fragments_are_reassembled() {
+        // Unmasked frames as a server would send: "Hel", ping, "lo"
+        let mut input = BytesMut::from(&b"\x01\x03Hel\x89\x00\x80\x02lo"[..]);
+        let mut codec = WebSocketCodec::new(Role::Client);
+        assert_eq!(
+            codec.decode(&mut input).unwrap(),
+            Some(Message::Ping(Vec::new()))
+        );
+        assert_eq!(
+            codec.decode(&mut input).unwrap(),
+            Some(Message::Text("Hello".to_owned()))
+        );
+    }
+
+    #[test]
+    fn masked_frames_are_rejected_by_clients() {
+        let mut input = BytesMut::from(&b"\x81\x80\x00\x00\x00\x00"[..]);
+        let mut codec = WebSocketCodec::new(Role::Client);
+        assert!(codec.decode(&mut input).is_err());
+    }
+}
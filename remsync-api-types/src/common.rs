@@ -1,16 +1,66 @@
-///! Common types used in both requests and responses
-use serde::{Deserialize, Serialize};
+//! Common types used in both requests and responses
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Fields which a type doesn't know about
+///
+/// Newer firmware and servers may add fields to any of the API and on-disk
+/// formats.  Rather than dropping them, each type collects the fields it
+/// doesn't recognise in one of these and writes them out again when it is
+/// serialised, so passing data through remsync doesn't lose anything.
+pub type UnknownFields = serde_json::Map<String, serde_json::Value>;
 
 /// A node's type
 ///
 /// Every node that exists in the system has a type.
 ///
 /// Documents, be they epubs, notebooks, or pdfs, are one type,
-/// and folders are another.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// and folders are another.  Types we don't know about are kept by name.
+///
+/// ```
+/// # use remsync_api_types::NodeType;
+/// let node_type: NodeType = serde_json::from_str(r#""TemplateType""#).unwrap();
+/// assert_eq!(node_type, NodeType::Unknown("TemplateType".to_owned()));
+/// assert_eq!(serde_json::to_string(&node_type).unwrap(), r#""TemplateType""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum NodeType {
     /// Folders have this type
     CollectionType,
     /// Documents have this type
     DocumentType,
+    /// Some other type, by name
+    Unknown(String),
+}
+
+impl NodeType {
+    /// The name of the type, as written in the API and on disk
+    pub fn as_str(&self) -> &str {
+        match self {
+            NodeType::CollectionType => "CollectionType",
+            NodeType::DocumentType => "DocumentType",
+            NodeType::Unknown(name) => name,
+        }
+    }
+}
+
+impl From<&str> for NodeType {
+    fn from(name: &str) -> Self {
+        match name {
+            "CollectionType" => NodeType::CollectionType,
+            "DocumentType" => NodeType::DocumentType,
+            _ => NodeType::Unknown(name.to_owned()),
+        }
+    }
+}
+
+impl Serialize for NodeType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(NodeType::from(&*String::deserialize(deserializer)?))
+    }
 }
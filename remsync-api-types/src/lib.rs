@@ -2,14 +2,20 @@
 //!
 
 mod common;
+mod node_id;
 mod notifications;
 mod requests;
 mod responses;
+mod timestamp;
 mod tokens;
 
 // The basic concept is that we'll import these types at the top level
 // rather than expecting users to use the deeper paths
 pub use common::*;
+pub use node_id::{
+    from_received_reader, from_received_slice, unchecked, NodeId, NodeIdError, ParentRef,
+    ReceivedIds,
+};
 pub use notifications::*;
 pub use requests::auth::DeviceTokenRequest;
 pub use requests::delete::DeleteRequest;
@@ -18,6 +24,7 @@ pub use responses::delete::DeleteResponse;
 pub use responses::discovery::DiscoveryResponse;
 pub use responses::docs::DocsResponse;
 pub use responses::upload::{UpdateStatusResponse, UploadRequestResponse};
+pub use timestamp::{now_epoch_secs, Timestamp, TimestampError};
 pub use tokens::Auth0Profile;
 pub use tokens::DeviceToken;
 pub use tokens::UserToken;
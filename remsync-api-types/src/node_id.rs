@@ -0,0 +1,470 @@
+//! Node IDs and parent references
+//!
+//! Every node is identified by a UUID, and names its parent by that
+//! parent's UUID, with `""` standing for the top level and `"trash"` for
+//! the trash.  A [`NodeId`] is only ever a valid ID, so it can't be mixed up
+//! with a name, and a [`ParentRef`] spells out which kind of parent it is.
+//!
+//! IDs are checked to be UUIDs when they are deserialised.  Servers which
+//! are not quite the official one may hand out other IDs, so the replies
+//! and files which hold IDs implement [`ReceivedIds`], and are read with
+//! [`from_received_slice`] or [`from_received_reader`], which say how
+//! strictly to check them.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The parent of nodes in the trash
+const TRASH: &str = "trash";
+
+/// The ID of a node
+///
+/// ```
+/// # use remsync_api_types::NodeId;
+/// let id: NodeId = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+/// assert_eq!(id, "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
+/// assert!("WiFi and USB local sync".parse::<NodeId>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(String);
+
+/// The error returned when a node ID isn't acceptable
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeIdError(String);
+
+impl fmt::Display for NodeIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid node ID {:?}", self.0)
+    }
+}
+
+impl std::error::Error for NodeIdError {}
+
+/// Whether `text` is a UUID in its usual hyphenated form
+fn is_uuid(text: &str) -> bool {
+    text.len() == 36
+        && text.bytes().enumerate().all(|(n, b)| match n {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+impl NodeId {
+    /// Parse a node ID, which must be a UUID
+    pub fn parse(text: &str) -> Result<Self, NodeIdError> {
+        if is_uuid(text) {
+            Ok(Self(text.to_owned()))
+        } else {
+            Err(NodeIdError(text.to_owned()))
+        }
+    }
+
+    /// Parse a node ID without insisting that it is a UUID
+    ///
+    /// Any text is accepted, so long as there is some.
+    ///
+    /// ```
+    /// # use remsync_api_types::NodeId;
+    /// assert_eq!(NodeId::parse_lenient("some-id").unwrap(), "some-id");
+    /// assert!(NodeId::parse_lenient("").is_err());
+    /// ```
+    pub fn parse_lenient(text: &str) -> Result<Self, NodeIdError> {
+        if text.is_empty() {
+            Err(NodeIdError(text.to_owned()))
+        } else {
+            Ok(Self(text.to_owned()))
+        }
+    }
+
+    /// Parse a node ID from elsewhere, as [`NodeId::parse_lenient`] does
+    /// if `lenient` is set and as [`NodeId::parse`] does otherwise
+    ///
+    /// ```
+    /// # use remsync_api_types::NodeId;
+    /// assert!(NodeId::parse_received("RM102-928-57210", true).is_ok());
+    /// assert!(NodeId::parse_received("RM102-928-57210", false).is_err());
+    /// ```
+    pub fn parse_received(text: &str, lenient: bool) -> Result<Self, NodeIdError> {
+        if lenient {
+            Self::parse_lenient(text)
+        } else {
+            Self::parse(text)
+        }
+    }
+
+    /// The text of the ID
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for NodeId {
+    type Err = NodeIdError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for NodeId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for NodeId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for NodeId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for NodeId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Serialize for NodeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The parent of a node
+///
+/// ```
+/// # use remsync_api_types::ParentRef;
+/// assert_eq!("".parse::<ParentRef>().unwrap(), ParentRef::Root);
+/// assert_eq!("trash".parse::<ParentRef>().unwrap(), ParentRef::Trash);
+/// let parent: ParentRef = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+/// assert_eq!(parent, "e0c1c79f-b491-45e7-a431-a46fe1ec8a66");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ParentRef {
+    /// The node is at the top level, written `""`
+    #[default]
+    Root,
+    /// The node is in the trash, written `"trash"`
+    Trash,
+    /// The node is in the given collection
+    Node(NodeId),
+}
+
+impl ParentRef {
+    /// Parse a parent, whose ID must be a UUID
+    pub fn parse(text: &str) -> Result<Self, NodeIdError> {
+        Self::parse_with(text, NodeId::parse)
+    }
+
+    /// Parse a parent from elsewhere, as [`NodeId::parse_received`] does
+    pub fn parse_received(text: &str, lenient: bool) -> Result<Self, NodeIdError> {
+        Self::parse_with(text, |text| NodeId::parse_received(text, lenient))
+    }
+
+    fn parse_with(
+        text: &str,
+        node: impl FnOnce(&str) -> Result<NodeId, NodeIdError>,
+    ) -> Result<Self, NodeIdError> {
+        match text {
+            "" => Ok(ParentRef::Root),
+            TRASH => Ok(ParentRef::Trash),
+            _ => node(text).map(ParentRef::Node),
+        }
+    }
+
+    /// The parent as written in the API and on disk
+    ///
+    /// ```
+    /// # use remsync_api_types::ParentRef;
+    /// assert_eq!(ParentRef::Root.as_str(), "");
+    /// assert_eq!(ParentRef::Trash.as_str(), "trash");
+    /// ```
+    pub fn as_str(&self) -> &str {
+        match self {
+            ParentRef::Root => "",
+            ParentRef::Trash => TRASH,
+            ParentRef::Node(id) => id.as_str(),
+        }
+    }
+
+    /// The ID of the parent collection, unless this is the root or trash
+    pub fn node_id(&self) -> Option<&NodeId> {
+        match self {
+            ParentRef::Node(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Whether this is the top level
+    pub fn is_root(&self) -> bool {
+        *self == ParentRef::Root
+    }
+
+    /// Whether this is the trash
+    pub fn is_trash(&self) -> bool {
+        *self == ParentRef::Trash
+    }
+}
+
+impl From<NodeId> for ParentRef {
+    fn from(id: NodeId) -> Self {
+        ParentRef::Node(id)
+    }
+}
+
+impl FromStr for ParentRef {
+    type Err = NodeIdError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for ParentRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for ParentRef {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ParentRef {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<NodeId> for ParentRef {
+    fn eq(&self, other: &NodeId) -> bool {
+        self.node_id() == Some(other)
+    }
+}
+
+impl Serialize for ParentRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ParentRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Values holding IDs which were received from a server or read from disk
+///
+/// Only whoever receives such a value knows whether its IDs must be UUIDs,
+/// so they are deserialised unchecked, as [`NodeId::parse_lenient`] would,
+/// and then checked with [`check_ids`](Self::check_ids).  Use
+/// [`from_received_slice`] or [`from_received_reader`] to do both.
+pub trait ReceivedIds {
+    /// Check every ID, as [`NodeId::parse_received`] would
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError>;
+}
+
+impl ReceivedIds for NodeId {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        NodeId::parse_received(&self.0, lenient).map(|_| ())
+    }
+}
+
+impl ReceivedIds for ParentRef {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.node_id().map_or(Ok(()), |id| id.check_ids(lenient))
+    }
+}
+
+impl<T: ReceivedIds> ReceivedIds for Option<T> {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.as_ref()
+            .map_or(Ok(()), |value| value.check_ids(lenient))
+    }
+}
+
+impl<T: ReceivedIds> ReceivedIds for Vec<T> {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.iter().try_for_each(|value| value.check_ids(lenient))
+    }
+}
+
+/// Deserialise a value received from a server from JSON, insisting that its
+/// IDs are UUIDs unless `lenient`
+///
+/// ```
+/// # use remsync_api_types::*;
+/// let json = br#"[{"Success":true,"Message":"","ID":"RM102-928-57210","Version":2}]"#;
+/// let replies: Vec<DeleteResponse> = from_received_slice(json, true).unwrap();
+/// assert_eq!(replies[0].id().unwrap(), "RM102-928-57210");
+/// assert!(from_received_slice::<Vec<DeleteResponse>>(json, false).is_err());
+/// ```
+pub fn from_received_slice<T>(json: &[u8], lenient: bool) -> serde_json::Result<T>
+where
+    T: DeserializeOwned + ReceivedIds,
+{
+    checked(serde_json::from_slice(json)?, lenient)
+}
+
+/// Deserialise a value read from disk as JSON, insisting that its IDs are
+/// UUIDs unless `lenient`
+pub fn from_received_reader<R, T>(reader: R, lenient: bool) -> serde_json::Result<T>
+where
+    R: Read,
+    T: DeserializeOwned + ReceivedIds,
+{
+    checked(serde_json::from_reader(reader)?, lenient)
+}
+
+fn checked<T: ReceivedIds>(value: T, lenient: bool) -> serde_json::Result<T> {
+    value.check_ids(lenient).map_err(serde::de::Error::custom)?;
+    Ok(value)
+}
+
+/// A node ID deserialised without being checked, see [`ReceivedIds`]
+struct Unchecked(NodeId);
+
+impl<'de> Deserialize<'de> for Unchecked {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        NodeId::parse_lenient(&text)
+            .map(Unchecked)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Deserialisation of received IDs, which are checked later by
+/// [`ReceivedIds`]
+///
+/// These are for use with `#[serde(deserialize_with)]`.
+pub mod unchecked {
+    use super::{NodeId, ParentRef, Unchecked};
+    use serde::{Deserialize, Deserializer};
+
+    /// Deserialise a node ID without checking it
+    pub fn node_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NodeId, D::Error> {
+        Unchecked::deserialize(deserializer).map(|id| id.0)
+    }
+
+    /// Deserialise a parent without checking its ID
+    pub fn parent<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ParentRef, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        ParentRef::parse_with(&text, NodeId::parse_lenient).map_err(serde::de::Error::custom)
+    }
+
+    /// Deserialise a list of node IDs without checking them
+    pub fn node_ids<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<NodeId>>, D::Error> {
+        let ids = Vec::<Unchecked>::deserialize(deserializer)?;
+        Ok(Some(ids.into_iter().map(|id| id.0).collect()))
+    }
+}
+
+/// (De)serialisation of IDs which are left empty in some replies
+///
+/// Failed requests may be answered without an ID, which is written `""`.
+/// The IDs are not checked, see [`ReceivedIds`].
+pub(crate) mod optional {
+    use super::NodeId;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(id: &Option<NodeId>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(id.as_ref().map_or("", NodeId::as_str))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<NodeId>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            NodeId::parse_lenient(&text)
+                .map(Some)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ids_are_validated() {
+        let json = r#""092FD1CC-df38-4fc5-8633-3a8a15a2a316""#;
+        let id: NodeId = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&id).unwrap(), json);
+        for bad in &[
+            r#""""#,
+            r#""Quick sheets""#,
+            r#""092fd1cc-df38-4fc5-8633-3a8a15a2a31""#,
+            r#""092fd1cc+df38-4fc5-8633-3a8a15a2a316""#,
+            r#""092fd1cc-df38-4fc5-8633-3a8a15a2a31g""#,
+        ] {
+            assert!(serde_json::from_str::<NodeId>(bad).is_err(), "{}", bad);
+        }
+        assert!(NodeId::parse_lenient("RM102-928-57210").is_ok());
+    }
+
+    #[test]
+    fn parents_round_trip() {
+        for (json, parent) in &[
+            (r#""""#, ParentRef::Root),
+            (r#""trash""#, ParentRef::Trash),
+            (
+                r#""e0c1c79f-b491-45e7-a431-a46fe1ec8a66""#,
+                ParentRef::Node("e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap()),
+            ),
+        ] {
+            assert_eq!(serde_json::from_str::<ParentRef>(json).unwrap(), *parent);
+            assert_eq!(serde_json::to_string(parent).unwrap(), *json);
+        }
+        assert!(serde_json::from_str::<ParentRef>(r#""Trash""#).is_err());
+    }
+
+    #[test]
+    fn received_ids_may_be_lenient() {
+        let text = "RM102-928-57210";
+        assert!(ParentRef::parse_received(text, false).is_err());
+        assert_eq!(ParentRef::parse_received(text, true).unwrap(), text);
+        assert_eq!(
+            ParentRef::parse_received("trash", false).unwrap(),
+            ParentRef::Trash
+        );
+        let json = format!("{:?}", text);
+        assert!(serde_json::from_str::<ParentRef>(&json).is_err());
+        let parent = unchecked::parent(&mut serde_json::Deserializer::from_str(&json)).unwrap();
+        assert!(parent.check_ids(false).is_err());
+        assert!(parent.check_ids(true).is_ok());
+    }
+}
@@ -1,15 +1,54 @@
 //! Types for the Notifications
 
-use crate::NodeType;
+use crate::{NodeId, NodeIdError, NodeType, ParentRef, ReceivedIds, Timestamp, UnknownFields};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// The type of a notification event
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+///
+/// Events we don't know about are kept by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NotificationEventType {
     /// A document/collection was added/modified
     DocAdded,
     /// A document/collection was removed
     DocDeleted,
+    /// Some other event, by name
+    Unknown(String),
+}
+
+impl NotificationEventType {
+    /// The name of the event, as written in notifications
+    pub fn as_str(&self) -> &str {
+        match self {
+            NotificationEventType::DocAdded => "DocAdded",
+            NotificationEventType::DocDeleted => "DocDeleted",
+            NotificationEventType::Unknown(name) => name,
+        }
+    }
+}
+
+impl From<&str> for NotificationEventType {
+    fn from(name: &str) -> Self {
+        match name {
+            "DocAdded" => NotificationEventType::DocAdded,
+            "DocDeleted" => NotificationEventType::DocDeleted,
+            _ => NotificationEventType::Unknown(name.to_owned()),
+        }
+    }
+}
+
+impl Serialize for NotificationEventType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NotificationEventType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(NotificationEventType::from(&*String::deserialize(
+            deserializer,
+        )?))
+    }
 }
 
 /// Attributes for a notification message.
@@ -30,9 +69,11 @@ pub struct NotificationMessageAttributes {
     /// The event kind
     event: NotificationEventType,
     /// The ID of the node the event is about
-    id: String,
+    #[serde(deserialize_with = "crate::unchecked::node_id")]
+    id: NodeId,
     /// The parent of the node the event is about
-    parent: String,
+    #[serde(deserialize_with = "crate::unchecked::parent")]
+    parent: ParentRef,
     #[serde(rename = "sourceDeviceDesc")]
     /// The device descriptor registered to the user ID which made the change
     source_device_desc: String,
@@ -51,6 +92,9 @@ pub struct NotificationMessageAttributes {
     #[serde(rename = "vissibleName")]
     /// The name of the node after the change
     name: String,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
 }
 
 impl NotificationMessageAttributes {
@@ -60,18 +104,21 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// let attrs = NotificationMessageAttributes::new(
-    ///     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    ///     "some-parent-id", "some-device-desc", "some-device-id",
+    ///     "some-user-id", false, NotificationEventType::DocAdded, id,
+    ///     parent, "some-device-desc", "some-device-id",
     ///     NodeType::CollectionType, 7, "My Shiny Node"
     /// );
     /// ```
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         auth0_user_id: &str,
         bookmarked: bool,
         event: NotificationEventType,
-        id: &str,
-        parent: &str,
+        id: NodeId,
+        parent: ParentRef,
         source_device_desc: &str,
         source_device_id: &str,
         node_type: NodeType,
@@ -82,13 +129,14 @@ impl NotificationMessageAttributes {
             auth0_user_id: auth0_user_id.to_owned(),
             bookmarked,
             event,
-            id: id.to_owned(),
-            parent: parent.to_owned(),
+            id,
+            parent,
             source_device_desc: source_device_desc.to_owned(),
             source_device_id: source_device_id.to_owned(),
             node_type,
             version,
             name: name.to_owned(),
+            unknown_fields: UnknownFields::new(),
         }
     }
 
@@ -96,9 +144,11 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
     /// assert_eq!(attrs.auth0_user_id(), "some-user-id");
@@ -111,9 +161,11 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
     /// assert_eq!(attrs.bookmarked(), false);
@@ -126,29 +178,33 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// assert_eq!(attrs.event(), NotificationEventType::DocAdded);
+    /// assert_eq!(attrs.event(), &NotificationEventType::DocAdded);
     /// ```
-    pub fn event(&self) -> NotificationEventType {
-        self.event
+    pub fn event(&self) -> &NotificationEventType {
+        &self.event
     }
 
     /// Retrieve the node ID from an attributes object.
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// assert_eq!(attrs.id(), "some-id");
+    /// assert_eq!(attrs.id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
     /// ```
-    pub fn id(&self) -> &str {
+    pub fn id(&self) -> &NodeId {
         &self.id
     }
 
@@ -156,14 +212,16 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// assert_eq!(attrs.parent(), "some-parent-id");
+    /// assert_eq!(attrs.parent(), "e0c1c79f-b491-45e7-a431-a46fe1ec8a66");
     /// ```
-    pub fn parent(&self) -> &str {
+    pub fn parent(&self) -> &ParentRef {
         &self.parent
     }
 
@@ -171,9 +229,11 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
     /// assert_eq!(attrs.source_device_desc(), "some-device-desc");
@@ -186,9 +246,11 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
     /// assert_eq!(attrs.source_device_id(), "some-device-id");
@@ -201,24 +263,28 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// assert_eq!(attrs.node_type(), NodeType::CollectionType);
+    /// assert_eq!(attrs.node_type(), &NodeType::CollectionType);
     /// ```
-    pub fn node_type(&self) -> NodeType {
-        self.node_type
+    pub fn node_type(&self) -> &NodeType {
+        &self.node_type
     }
 
     /// Retrieve the node version from an attributes object.
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
     /// assert_eq!(attrs.version(), 7);
@@ -231,9 +297,11 @@ impl NotificationMessageAttributes {
     ///
     /// ```
     /// # use remsync_api_types::{NodeType, NotificationEventType, NotificationMessageAttributes};
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
     /// assert_eq!(attrs.name(), "My Shiny Node");
@@ -241,6 +309,11 @@ impl NotificationMessageAttributes {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Retrieve the fields of a NotificationMessageAttributes which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
 }
 
 /// A Notification message
@@ -258,9 +331,19 @@ pub struct NotificationMessage {
     message_id: String,
     #[serde(rename = "publishTime")]
     /// The publishing time of the message
-    publish_time_: String,
+    publish_time_: Timestamp,
     /// The publishing time of the message
-    publish_time: String,
+    publish_time: Timestamp,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
+}
+
+impl ReceivedIds for NotificationMessageAttributes {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.id.check_ids(lenient)?;
+        self.parent.check_ids(lenient)
+    }
 }
 
 impl NotificationMessage {
@@ -270,24 +353,29 @@ impl NotificationMessage {
     ///
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
+    /// let msg = NotificationMessage::new(
+    ///     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// );
     /// ```
     pub fn new(
         attributes: NotificationMessageAttributes,
         message_id: &str,
-        publish_time: &str,
+        publish_time: Timestamp,
     ) -> Self {
         Self {
             attributes,
             message_id_: message_id.to_owned(),
             message_id: message_id.to_owned(),
-            publish_time_: publish_time.to_owned(),
-            publish_time: publish_time.to_owned(),
+            publish_time_: publish_time.clone(),
+            publish_time,
+            unknown_fields: UnknownFields::new(),
         }
     }
 
@@ -297,13 +385,17 @@ impl NotificationMessage {
     ///
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// # let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
-    /// assert_eq!(msg.attributes().id(), "some-id");
+    /// # let msg = NotificationMessage::new(
+    /// #     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(msg.attributes().id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
     /// ```
     pub fn attributes(&self) -> &NotificationMessageAttributes {
         &self.attributes
@@ -313,12 +405,16 @@ impl NotificationMessage {
     ///
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// # let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
+    /// # let msg = NotificationMessage::new(
+    /// #     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// # );
     /// assert_eq!(msg.message_id(), "some-message-id");
     /// ```
     pub fn message_id(&self) -> &str {
@@ -329,17 +425,26 @@ impl NotificationMessage {
     ///
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// # let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
-    /// assert_eq!(msg.publish_time(), "some-publish-time");
+    /// # let msg = NotificationMessage::new(
+    /// #     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(msg.publish_time(), "2019-08-31T15:36:45.576Z");
     /// ```
-    pub fn publish_time(&self) -> &str {
+    pub fn publish_time(&self) -> &Timestamp {
         &self.publish_time
     }
+
+    /// Retrieve the fields of a NotificationMessage which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
 }
 
 /// An actual notification event
@@ -352,6 +457,15 @@ pub struct NotificationEvent {
     message: NotificationMessage,
     /// The string name of the subscription channel
     subscription: String,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
+}
+
+impl ReceivedIds for NotificationMessage {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.attributes.check_ids(lenient)
+    }
 }
 
 impl NotificationEvent {
@@ -360,18 +474,23 @@ impl NotificationEvent {
     /// Notification events consist of a subscription name and a message.
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// # let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
+    /// # let msg = NotificationMessage::new(
+    /// #     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// # );
     /// let evt = NotificationEvent::new(msg, "some-subscription-name");
     /// ```
     pub fn new(message: NotificationMessage, subscription: &str) -> Self {
         Self {
             message,
             subscription: subscription.to_owned(),
+            unknown_fields: UnknownFields::new(),
         }
     }
 
@@ -381,12 +500,16 @@ impl NotificationEvent {
     ///
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// # let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
+    /// # let msg = NotificationMessage::new(
+    /// #     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// # );
     /// # let evt = NotificationEvent::new(msg, "some-subscription-name");
     /// assert_eq!(evt.message().message_id(), "some-message-id");
     /// ```
@@ -398,18 +521,27 @@ impl NotificationEvent {
     ///
     /// ```
     /// # use remsync_api_types::*;
+    /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+    /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
     /// # let attrs = NotificationMessageAttributes::new(
-    /// #     "some-user-id", false, NotificationEventType::DocAdded, "some-id",
-    /// #     "some-parent-id", "some-device-desc", "some-device-id",
+    /// #     "some-user-id", false, NotificationEventType::DocAdded, id,
+    /// #     parent, "some-device-desc", "some-device-id",
     /// #     NodeType::CollectionType, 7, "My Shiny Node"
     /// # );
-    /// # let msg = NotificationMessage::new(attrs, "some-message-id", "some-publish-time");
+    /// # let msg = NotificationMessage::new(
+    /// #     attrs, "some-message-id", "2019-08-31T15:36:45.576Z".parse().unwrap()
+    /// # );
     /// # let evt = NotificationEvent::new(msg, "some-subscription-name");
     /// assert_eq!(evt.subscription(), "some-subscription-name");
     /// ```
     pub fn subscription(&self) -> &str {
         &self.subscription
     }
+
+    /// Retrieve the fields of a NotificationEvent which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
 }
 
 /// Deserialize bool from String with custom value mapping
@@ -450,6 +582,12 @@ where
     serializer.serialize_str(&val)
 }
 
+impl ReceivedIds for NotificationEvent {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.message.check_ids(lenient)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::*;
@@ -521,4 +659,44 @@ mod test {
 "#,
         )
     }
+
+    #[test]
+    fn unknown_event() {
+        let event: NotificationEvent = from_str(
+            r#"
+{
+  "message": {
+    "attributes": {
+      "auth0UserID": "auth0|5d67c7af9584340e0f1ec3d5",
+      "bookmarked": "false",
+      "event": "DocArchived",
+      "id": "092fd1cc-df38-4fc5-8633-3a8a15a2a316",
+      "parent": "",
+      "sourceDeviceDesc": "remarkable",
+      "sourceDeviceID": "RM102-928-57210",
+      "type": "DocumentType",
+      "version": "4",
+      "vissibleName": "WiFi and USB local sync",
+      "archivedBy": "remarkable"
+    },
+    "messageId": "700982536103224",
+    "message_id": "700982536103224",
+    "publishTime": "2019-08-31T14:52:54.158Z",
+    "publish_time": "2019-08-31T14:52:54.158Z"
+  },
+  "subscription": "projects/remarkable-production/subscriptions/sub-gm1h-notifications-production"
+}
+"#,
+        )
+        .expect("Unable to parse");
+        let attrs = event.message().attributes();
+        assert_eq!(
+            attrs.event(),
+            &NotificationEventType::Unknown("DocArchived".to_owned())
+        );
+        assert_eq!(attrs.unknown_fields()["archivedBy"], "remarkable");
+        let s = to_string_pretty(&event).expect("Unable to reserialize");
+        assert!(s.contains(r#""event": "DocArchived""#));
+        assert!(s.contains(r#""archivedBy": "remarkable""#));
+    }
 }
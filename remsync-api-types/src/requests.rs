@@ -3,6 +3,7 @@
 pub mod auth {
     //! Authentication types
 
+    use crate::UnknownFields;
     use serde::{Deserialize, Serialize};
 
     /// Establish new device bearer token
@@ -19,6 +20,9 @@ pub mod auth {
         #[serde(rename = "deviceID")]
         /// Device ID - a UUID as a string, or perhaps a serial number.  Unclear
         device_id: String,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl DeviceTokenRequest {
@@ -36,6 +40,7 @@ pub mod auth {
                 code: code.to_owned(),
                 device_desc: device_desc.to_owned(),
                 device_id: device_id.to_owned(),
+                unknown_fields: UnknownFields::new(),
             }
         }
 
@@ -77,13 +82,18 @@ pub mod auth {
         pub fn device_id(&self) -> &str {
             &self.device_id
         }
+
+        /// Retrieve the fields of a DeviceTokenRequest which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
     }
 }
 
 pub mod upload {
     //! Requests for document/node uploading
 
-    use crate::NodeType;
+    use crate::{NodeId, NodeType, ParentRef, Timestamp, UnknownFields};
     use serde::{Deserialize, Serialize};
 
     /// A request to be permitted to upload a node
@@ -94,16 +104,19 @@ pub mod upload {
     pub struct UploadRequestRequest {
         #[serde(rename = "ID")]
         /// The ID of the node (UUID)
-        id: String,
+        id: NodeId,
         #[serde(rename = "Parent")]
-        /// The ID of the node's parent (UUID)
-        parent: String,
+        /// The node's parent
+        parent: ParentRef,
         #[serde(rename = "Type")]
         /// The type of this node
         node_type: NodeType,
         #[serde(rename = "Version")]
         /// The version of this node
         version: usize,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl UploadRequestRequest {
@@ -112,17 +125,20 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UploadRequestRequest;
-        /// let upload = UploadRequestRequest::new("some-id",
-        ///                                        "some-parent-id",
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// let upload = UploadRequestRequest::new(id,
+        ///                                        parent,
         ///                                        NodeType::CollectionType,
         ///                                        1);
         /// ```
-        pub fn new(id: &str, parent: &str, node_type: NodeType, version: usize) -> Self {
+        pub fn new(id: NodeId, parent: ParentRef, node_type: NodeType, version: usize) -> Self {
             Self {
-                id: id.to_owned(),
-                parent: parent.to_owned(),
+                id,
+                parent,
                 node_type,
                 version,
+                unknown_fields: UnknownFields::new(),
             }
         }
 
@@ -131,13 +147,15 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UploadRequestRequest;
-        /// # let upload = UploadRequestRequest::new("some-id",
-        /// #                                        "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UploadRequestRequest::new(id,
+        /// #                                        parent,
         /// #                                        NodeType::CollectionType,
         /// #                                        1);
-        /// assert_eq!(upload.id(), "some-id");
+        /// assert_eq!(upload.id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
+        pub fn id(&self) -> &NodeId {
             &self.id
         }
 
@@ -146,13 +164,15 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UploadRequestRequest;
-        /// # let upload = UploadRequestRequest::new("some-id",
-        /// #                                        "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UploadRequestRequest::new(id,
+        /// #                                        parent,
         /// #                                        NodeType::CollectionType,
         /// #                                        1);
-        /// assert_eq!(upload.parent(), "some-parent-id");
+        /// assert_eq!(upload.parent(), "e0c1c79f-b491-45e7-a431-a46fe1ec8a66");
         /// ```
-        pub fn parent(&self) -> &str {
+        pub fn parent(&self) -> &ParentRef {
             &self.parent
         }
 
@@ -161,14 +181,16 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UploadRequestRequest;
-        /// # let upload = UploadRequestRequest::new("some-id",
-        /// #                                        "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UploadRequestRequest::new(id,
+        /// #                                        parent,
         /// #                                        NodeType::CollectionType,
         /// #                                        1);
-        /// assert_eq!(upload.node_type(), NodeType::CollectionType);
+        /// assert_eq!(upload.node_type(), &NodeType::CollectionType);
         /// ```
-        pub fn node_type(&self) -> NodeType {
-            self.node_type
+        pub fn node_type(&self) -> &NodeType {
+            &self.node_type
         }
 
         /// Retrieve the version of an UploadRequestRequest
@@ -176,8 +198,10 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UploadRequestRequest;
-        /// # let upload = UploadRequestRequest::new("some-id",
-        /// #                                        "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UploadRequestRequest::new(id,
+        /// #                                        parent,
         /// #                                        NodeType::CollectionType,
         /// #                                        1);
         /// assert_eq!(upload.version(), 1);
@@ -185,6 +209,11 @@ pub mod upload {
         pub fn version(&self) -> usize {
             self.version
         }
+
+        /// Retrieve the fields of an UploadRequestRequest which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
     }
 
     /// A request to update the status of a node
@@ -196,10 +225,10 @@ pub mod upload {
     pub struct UpdateStatusRequest {
         #[serde(rename = "ID")]
         /// The ID of the node to update
-        id: String,
+        id: NodeId,
         #[serde(rename = "Parent")]
         /// The Parent of that node
-        parent: String,
+        parent: ParentRef,
         #[serde(rename = "Type")]
         /// The type of this node
         node_type: NodeType,
@@ -217,7 +246,10 @@ pub mod upload {
         name: String,
         #[serde(rename = "ModifiedClient")]
         /// When this node was last modified on a client
-        modified_client: String,
+        modified_client: Timestamp,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl UpdateStatusRequest {
@@ -226,53 +258,86 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// let upload = UpdateStatusRequest::new("some-id",
-        ///                                       "some-parent-id",
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// let upload = UpdateStatusRequest::new(id,
+        ///                                       parent,
         ///                                       NodeType::CollectionType,
         ///                                       1,
         ///                                       false,
         ///                                       0,
         ///                                       "My Nice Folder",
-        ///                                       "2019-08-31T14:49:51.302302Z");
+        ///                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
         /// ```
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
-            id: &str,
-            parent: &str,
+            id: NodeId,
+            parent: ParentRef,
             node_type: NodeType,
             version: usize,
             bookmarked: bool,
             current_page: usize,
             name: &str,
-            modified_client: &str,
+            modified_client: &Timestamp,
         ) -> Self {
             Self {
-                id: id.to_owned(),
-                parent: parent.to_owned(),
+                id,
+                parent,
                 node_type,
                 version,
                 bookmarked,
                 current_page,
                 name: name.to_owned(),
-                modified_client: modified_client.to_owned(),
+                modified_client: modified_client.clone(),
+                unknown_fields: UnknownFields::new(),
             }
         }
 
+        /// Carry fields we don't know about along with an UpdateStatusRequest
+        ///
+        /// When updating a node, pass on the unknown fields of its
+        /// [`DocsResponse`](crate::DocsResponse) so that they aren't lost.
+        ///
+        /// ```
+        /// # use remsync_api_types::{NodeType, UnknownFields, UpdateStatusRequest};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// let mut unknown = UnknownFields::new();
+        /// unknown.insert("Pinned".to_owned(), true.into());
+        /// let upload = UpdateStatusRequest::new(id,
+        ///                                       parent,
+        ///                                       NodeType::CollectionType,
+        ///                                       1,
+        ///                                       false,
+        ///                                       0,
+        ///                                       "My Nice Folder",
+        ///                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap())
+        ///     .with_unknown_fields(unknown);
+        /// assert_eq!(upload.unknown_fields()["Pinned"], true);
+        /// ```
+        pub fn with_unknown_fields(mut self, unknown_fields: UnknownFields) -> Self {
+            self.unknown_fields = unknown_fields;
+            self
+        }
+
         /// Retrieve the ID of an UpdateStatusRequest
         ///
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
-        /// assert_eq!(upload.id(), "some-id");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
+        /// assert_eq!(upload.id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
+        pub fn id(&self) -> &NodeId {
             &self.id
         }
 
@@ -281,17 +346,19 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
-        /// assert_eq!(upload.parent(), "some-parent-id");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
+        /// assert_eq!(upload.parent(), "e0c1c79f-b491-45e7-a431-a46fe1ec8a66");
         /// ```
-        pub fn parent(&self) -> &str {
+        pub fn parent(&self) -> &ParentRef {
             &self.parent
         }
 
@@ -300,18 +367,20 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
-        /// assert_eq!(upload.node_type(), NodeType::CollectionType);
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
+        /// assert_eq!(upload.node_type(), &NodeType::CollectionType);
         /// ```
-        pub fn node_type(&self) -> NodeType {
-            self.node_type
+        pub fn node_type(&self) -> &NodeType {
+            &self.node_type
         }
 
         /// Retrieve the version of an UpdateStatusRequest
@@ -319,14 +388,16 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
         /// assert_eq!(upload.version(), 1);
         /// ```
         pub fn version(&self) -> usize {
@@ -338,14 +409,16 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
         /// assert_eq!(upload.bookmarked(), false);
         /// ```
         pub fn bookmarked(&self) -> bool {
@@ -357,14 +430,16 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
         /// assert_eq!(upload.current_page(), 0);
         /// ```
         pub fn current_page(&self) -> usize {
@@ -376,14 +451,16 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
         /// assert_eq!(upload.name(), "My Nice Folder");
         /// ```
         pub fn name(&self) -> &str {
@@ -395,24 +472,32 @@ pub mod upload {
         /// ```
         /// # use remsync_api_types::NodeType;
         /// # use remsync_api_types::UpdateStatusRequest;
-        /// # let upload = UpdateStatusRequest::new("some-id",
-        /// #                                       "some-parent-id",
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
+        /// # let upload = UpdateStatusRequest::new(id,
+        /// #                                       parent,
         /// #                                       NodeType::CollectionType,
         /// #                                       1,
         /// #                                       false,
         /// #                                       0,
         /// #                                       "My Nice Folder",
-        /// #                                       "2019-08-31T14:49:51.302302Z");
+        /// #                                       &"2019-08-31T14:49:51.302302Z".parse().unwrap());
         /// assert_eq!(upload.modified_client(), "2019-08-31T14:49:51.302302Z");
         /// ```
-        pub fn modified_client(&self) -> &str {
+        pub fn modified_client(&self) -> &Timestamp {
             &self.modified_client
         }
+
+        /// Retrieve the fields of an UpdateStatusRequest which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
     }
 }
 
 pub mod delete {
     //! Deletion types
+    use crate::{NodeId, UnknownFields};
     use serde::{Deserialize, Serialize};
 
     /// Request to delete a node from the API
@@ -423,10 +508,13 @@ pub mod delete {
     pub struct DeleteRequest {
         #[serde(rename = "ID")]
         /// The ID of the node to delete
-        id: String,
+        id: NodeId,
         #[serde(rename = "Version")]
         /// The version of the node to delete
         version: usize,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl DeleteRequest {
@@ -434,12 +522,14 @@ pub mod delete {
         ///
         /// ```
         /// # use remsync_api_types::DeleteRequest;
-        /// let delete = DeleteRequest::new("some-id", 4);
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// let delete = DeleteRequest::new(id, 4);
         /// ```
-        pub fn new(id: &str, version: usize) -> Self {
+        pub fn new(id: NodeId, version: usize) -> Self {
             Self {
-                id: id.to_owned(),
+                id,
                 version,
+                unknown_fields: UnknownFields::new(),
             }
         }
 
@@ -447,10 +537,11 @@ pub mod delete {
         ///
         /// ```
         /// # use remsync_api_types::DeleteRequest;
-        /// # let delete = DeleteRequest::new("some-id", 4);
-        /// assert_eq!(delete.id(), "some-id");
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let delete = DeleteRequest::new(id, 4);
+        /// assert_eq!(delete.id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
+        pub fn id(&self) -> &NodeId {
             &self.id
         }
 
@@ -458,12 +549,18 @@ pub mod delete {
         ///
         /// ```
         /// # use remsync_api_types::DeleteRequest;
-        /// # let delete = DeleteRequest::new("some-id", 4);
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let delete = DeleteRequest::new(id, 4);
         /// assert_eq!(delete.version(), 4);
         /// ```
         pub fn version(&self) -> usize {
             self.version
         }
+
+        /// Retrieve the fields of a DeleteRequest which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
     }
 }
 
@@ -3,6 +3,7 @@
 pub mod discovery {
     //! Discovery API responses
 
+    use crate::UnknownFields;
     use serde::{Deserialize, Serialize};
     /// A response from a service discovery request
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -14,6 +15,9 @@ pub mod discovery {
         /// The hostname discovered for the given service
         /// Note: This is only a hostname, port will always be 443 for https
         host: String,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl DiscoveryResponse {
@@ -27,6 +31,7 @@ pub mod discovery {
             Self {
                 status: status.to_owned(),
                 host: host.to_owned(),
+                unknown_fields: UnknownFields::new(),
             }
         }
 
@@ -62,6 +67,11 @@ pub mod discovery {
         pub fn into_host(self) -> String {
             self.host
         }
+
+        /// Retrieve the fields of a DiscoveryResponse which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
     }
 }
 
@@ -70,7 +80,7 @@ pub mod docs {
 
     use serde::{Deserialize, Serialize};
 
-    use crate::NodeType;
+    use crate::{NodeId, NodeIdError, NodeType, ParentRef, ReceivedIds, Timestamp, UnknownFields};
 
     /// A response to a request to the docs api
     ///
@@ -84,9 +94,9 @@ pub mod docs {
         #[serde(rename = "Message")]
         /// If the request was unsuccessful, this is the reason
         message: String,
-        #[serde(rename = "ID")]
+        #[serde(rename = "ID", deserialize_with = "crate::unchecked::node_id")]
         /// The ID of this node
-        id: String,
+        id: NodeId,
         #[serde(rename = "Version")]
         /// The version of this node
         version: usize,
@@ -96,10 +106,10 @@ pub mod docs {
         blob_url_get: String,
         #[serde(rename = "BlobURLGetExpires")]
         /// When the blob URL will expire
-        blob_url_get_expires: String,
+        blob_url_get_expires: Timestamp,
         #[serde(rename = "ModifiedClient")]
         /// When this node was last modified on a client device
-        modified_client: String,
+        modified_client: Timestamp,
         #[serde(rename = "Type")]
         /// The type of this node
         node_type: NodeType,
@@ -112,9 +122,12 @@ pub mod docs {
         #[serde(rename = "Bookmarked")]
         /// Whether or not this node is bookmarked
         bookmarked: bool,
-        #[serde(rename = "Parent")]
+        #[serde(rename = "Parent", deserialize_with = "crate::unchecked::parent")]
         /// The parent of this node
-        parent: String,
+        parent: ParentRef,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl DocsResponse {
@@ -124,51 +137,57 @@ pub mod docs {
         /// valid for documents.  It's zero for collection nodes.
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// let doc = DocsResponse::new(
-        ///     true, "", "some-id", 4, "some-url", "some-expiry",
-        ///     "some-modified", NodeType::DocumentType, "some-name",
-        ///     71, true, "some-parent-id"
+        ///     true, "", id, 4, "some-url", Timestamp::zero(),
+        ///     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        ///     71, true, parent
         /// );
         /// ```
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
             success: bool,
             message: &str,
-            id: &str,
+            id: NodeId,
             version: usize,
             blob_url_get: &str,
-            blob_url_get_expires: &str,
-            modified_client: &str,
+            blob_url_get_expires: Timestamp,
+            modified_client: Timestamp,
             node_type: NodeType,
             name: &str,
             current_page: usize,
             bookmarked: bool,
-            parent: &str,
+            parent: ParentRef,
         ) -> Self {
             Self {
                 success,
                 message: message.to_owned(),
-                id: id.to_owned(),
+                id,
                 version,
                 blob_url_get: blob_url_get.to_owned(),
-                blob_url_get_expires: blob_url_get_expires.to_owned(),
-                modified_client: modified_client.to_owned(),
-                node_type: node_type,
+                blob_url_get_expires,
+                modified_client,
+                node_type,
                 name: name.to_owned(),
                 current_page,
                 bookmarked,
-                parent: parent.to_owned(),
+                parent,
+                unknown_fields: UnknownFields::new(),
             }
         }
 
         /// Retrieve the success of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.success(), true);
         /// ```
@@ -179,11 +198,13 @@ pub mod docs {
         /// Retrieve the message of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.message(), "");
         /// ```
@@ -194,26 +215,30 @@ pub mod docs {
         /// Retrieve the ID of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
-        /// assert_eq!(doc.id(), "some-id");
+        /// assert_eq!(doc.id(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
+        pub fn id(&self) -> &NodeId {
             &self.id
         }
 
         /// Retrieve the version of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.version(), 4);
         /// ```
@@ -224,11 +249,13 @@ pub mod docs {
         /// Retrieve the BlobURLGet of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.blob_url_get(), "some-url");
         /// ```
@@ -239,56 +266,64 @@ pub mod docs {
         /// Retrieve the BlobURLGetExpires of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
-        /// assert_eq!(doc.blob_url_get_expires(), "some-expiry");
+        /// assert!(doc.blob_url_get_expires().is_zero());
         /// ```
-        pub fn blob_url_get_expires(&self) -> &str {
+        pub fn blob_url_get_expires(&self) -> &Timestamp {
             &self.blob_url_get_expires
         }
 
         /// Retrieve the client modification time of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
-        /// assert_eq!(doc.modified_client(), "some-modified");
+        /// assert_eq!(doc.modified_client(), "2019-09-01T10:29:26.440Z");
         /// ```
-        pub fn modified_client(&self) -> &str {
+        pub fn modified_client(&self) -> &Timestamp {
             &self.modified_client
         }
 
         /// Retrieve the node type of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
-        /// assert_eq!(doc.node_type(), NodeType::DocumentType);
+        /// assert_eq!(doc.node_type(), &NodeType::DocumentType);
         /// ```
-        pub fn node_type(&self) -> NodeType {
-            self.node_type
+        pub fn node_type(&self) -> &NodeType {
+            &self.node_type
         }
 
         /// Retrieve the name of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.name(), "some-name");
         /// ```
@@ -299,11 +334,13 @@ pub mod docs {
         /// Retrieve the current page of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.current_page(), 71);
         /// ```
@@ -314,11 +351,13 @@ pub mod docs {
         /// Retrieve the bookmarked status of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
         /// assert_eq!(doc.bookmarked(), true);
         /// ```
@@ -329,17 +368,31 @@ pub mod docs {
         /// Retrieve the parent ID of a DocsResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, DocsResponse};
+        /// # use remsync_api_types::{NodeType, DocsResponse, Timestamp};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
+        /// # let parent = "e0c1c79f-b491-45e7-a431-a46fe1ec8a66".parse().unwrap();
         /// # let doc = DocsResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
-        /// #     "some-modified", NodeType::DocumentType, "some-name",
-        /// #     71, true, "some-parent-id"
+        /// #     true, "", id, 4, "some-url", Timestamp::zero(),
+        /// #     "2019-09-01T10:29:26.440Z".parse().unwrap(), NodeType::DocumentType, "some-name",
+        /// #     71, true, parent
         /// # );
-        /// assert_eq!(doc.parent(), "some-parent-id");
+        /// assert_eq!(doc.parent(), "e0c1c79f-b491-45e7-a431-a46fe1ec8a66");
         /// ```
-        pub fn parent(&self) -> &str {
+        pub fn parent(&self) -> &ParentRef {
             &self.parent
         }
+
+        /// Retrieve the fields of a DocsResponse which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
+    }
+
+    impl ReceivedIds for DocsResponse {
+        fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+            self.id.check_ids(lenient)?;
+            self.parent.check_ids(lenient)
+        }
     }
 }
 
@@ -347,6 +400,8 @@ pub mod upload {
     //! Response types for uploads
     use serde::{Deserialize, Serialize};
 
+    use crate::{NodeId, NodeIdError, ReceivedIds, Timestamp, UnknownFields};
+
     /// A response to an UploadRequestRequest
     ///
     /// It's worth noting that these always come in lists even though the
@@ -360,8 +415,9 @@ pub mod upload {
         /// If the request was unsuccessful, this is the reason
         message: String,
         #[serde(rename = "ID")]
-        /// The ID of this node
-        id: String,
+        /// The ID of this node, which may be missing from failures
+        #[serde(with = "crate::node_id::optional")]
+        id: Option<NodeId>,
         #[serde(rename = "Version")]
         /// The version of this node
         version: usize,
@@ -370,7 +426,10 @@ pub mod upload {
         blob_url_put: String,
         #[serde(rename = "BlobURLPutExpires")]
         /// When the blob URL will expire
-        blob_url_put_expires: String,
+        blob_url_put_expires: Timestamp,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
 
     impl UploadRequestResponse {
@@ -379,35 +438,38 @@ pub mod upload {
         /// These are returned in lists from upload requests.
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// let upload = UploadRequestResponse::new(
-        ///     true, "", "some-id", 4, "some-url", "some-expiry",
+        ///     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// );
         /// ```
         pub fn new(
             success: bool,
             message: &str,
-            id: &str,
+            id: Option<NodeId>,
             version: usize,
             blob_url_put: &str,
-            blob_url_put_expires: &str,
+            blob_url_put_expires: Timestamp,
         ) -> Self {
             Self {
                 success,
                 message: message.to_owned(),
-                id: id.to_owned(),
+                id,
                 version,
                 blob_url_put: blob_url_put.to_owned(),
-                blob_url_put_expires: blob_url_put_expires.to_owned(),
+                blob_url_put_expires,
+                unknown_fields: UnknownFields::new(),
             }
         }
 
         /// Retrieve the success of an UploadRequestResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let upload = UploadRequestResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
+        /// #     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// # );
         /// assert_eq!(upload.success(), true);
         /// ```
@@ -418,9 +480,10 @@ pub mod upload {
         /// Retrieve the success of an UploadRequestResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let upload = UploadRequestResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
+        /// #     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// # );
         /// assert_eq!(upload.success(), true);
         /// ```
@@ -428,25 +491,27 @@ pub mod upload {
             &self.message
         }
 
-        /// Retrieve the ID of an UploadRequestResponse object
+        /// Retrieve the ID of an UploadRequestResponse object, if it has one
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let upload = UploadRequestResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
+        /// #     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// # );
-        /// assert_eq!(upload.id(), "some-id");
+        /// assert_eq!(upload.id().unwrap(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
-            &self.id
+        pub fn id(&self) -> Option<&NodeId> {
+            self.id.as_ref()
         }
 
         /// Retrieve the version of an UploadRequestResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let upload = UploadRequestResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
+        /// #     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// # );
         /// assert_eq!(upload.version(), 4);
         /// ```
@@ -457,9 +522,10 @@ pub mod upload {
         /// Retrieve the PUT url of an UploadRequestResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let upload = UploadRequestResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
+        /// #     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// # );
         /// assert_eq!(upload.blob_url_put(), "some-url");
         /// ```
@@ -470,15 +536,21 @@ pub mod upload {
         /// Retrieve the expiry time of the PUT url of an UploadRequestResponse object
         ///
         /// ```
-        /// # use remsync_api_types::{NodeType, UploadRequestResponse};
+        /// # use remsync_api_types::{Timestamp, UploadRequestResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let upload = UploadRequestResponse::new(
-        /// #     true, "", "some-id", 4, "some-url", "some-expiry",
+        /// #     true, "", Some(id), 4, "some-url", Timestamp::zero(),
         /// # );
-        /// assert_eq!(upload.blob_url_put_expires(), "some-expiry");
+        /// assert!(upload.blob_url_put_expires().is_zero());
         /// ```
-        pub fn blob_url_put_expires(&self) -> &str {
+        pub fn blob_url_put_expires(&self) -> &Timestamp {
             &self.blob_url_put_expires
         }
+
+        /// Retrieve the fields of an UploadRequestResponse which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
     }
 
     /// The response to an UpdateStatusRequest
@@ -495,11 +567,15 @@ pub mod upload {
         /// If the request was unsuccessful, this is the reason
         message: String,
         #[serde(rename = "ID")]
-        /// The ID of this node
-        id: String,
+        /// The ID of this node, which may be missing from failures
+        #[serde(with = "crate::node_id::optional")]
+        id: Option<NodeId>,
         #[serde(rename = "Version")]
         /// The version of this node
         version: usize,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
     impl UpdateStatusResponse {
         /// Create a new UpdateStatusResponse object
@@ -508,16 +584,18 @@ pub mod upload {
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, UpdateStatusResponse};
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// let update = UpdateStatusResponse::new(
-        ///     true, "", "some-id", 4,
+        ///     true, "", Some(id), 4,
         /// );
         /// ```
-        pub fn new(success: bool, message: &str, id: &str, version: usize) -> Self {
+        pub fn new(success: bool, message: &str, id: Option<NodeId>, version: usize) -> Self {
             Self {
                 success,
                 message: message.to_owned(),
-                id: id.to_owned(),
+                id,
                 version,
+                unknown_fields: UnknownFields::new(),
             }
         }
 
@@ -525,8 +603,9 @@ pub mod upload {
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, UpdateStatusResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let update = UpdateStatusResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
         /// assert_eq!(update.success(), true);
         /// ```
@@ -538,8 +617,9 @@ pub mod upload {
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, UpdateStatusResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let update = UpdateStatusResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
         /// assert_eq!(update.success(), true);
         /// ```
@@ -547,31 +627,50 @@ pub mod upload {
             &self.message
         }
 
-        /// Retrieve the ID of an UpdateStatusResponse object
+        /// Retrieve the ID of an UpdateStatusResponse object, if it has one
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, UpdateStatusResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let update = UpdateStatusResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
-        /// assert_eq!(update.id(), "some-id");
+        /// assert_eq!(update.id().unwrap(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
-            &self.id
+        pub fn id(&self) -> Option<&NodeId> {
+            self.id.as_ref()
         }
 
         /// Retrieve the version of an UpdateStatusResponse object
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, UpdateStatusResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let update = UpdateStatusResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
         /// assert_eq!(update.version(), 4);
         /// ```
         pub fn version(&self) -> usize {
             self.version
         }
+
+        /// Retrieve the fields of an UpdateStatusResponse which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
+    }
+
+    impl ReceivedIds for UploadRequestResponse {
+        fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+            self.id.check_ids(lenient)
+        }
+    }
+
+    impl ReceivedIds for UpdateStatusResponse {
+        fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+            self.id.check_ids(lenient)
+        }
     }
 }
 
@@ -579,6 +678,9 @@ pub mod delete {
     //! Deleting nodes
 
     use serde::{Deserialize, Serialize};
+
+    use crate::{NodeId, NodeIdError, ReceivedIds, UnknownFields};
+
     /// The response to a DeleteRequest
     ///
     /// These always come in lists as do the requests, though devices only ever
@@ -592,11 +694,15 @@ pub mod delete {
         /// If the request was unsuccessful, this is the reason
         message: String,
         #[serde(rename = "ID")]
-        /// The ID of this node
-        id: String,
+        /// The ID of this node, which may be missing from failures
+        #[serde(with = "crate::node_id::optional")]
+        id: Option<NodeId>,
         #[serde(rename = "Version")]
         /// The version of this node
         version: usize,
+        #[serde(flatten)]
+        /// Any fields we don't know about, kept so they aren't lost
+        unknown_fields: UnknownFields,
     }
     impl DeleteResponse {
         /// Create a new DeleteResponse object
@@ -605,16 +711,18 @@ pub mod delete {
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, DeleteResponse};
+        /// let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// let delete = DeleteResponse::new(
-        ///     true, "", "some-id", 4,
+        ///     true, "", Some(id), 4,
         /// );
         /// ```
-        pub fn new(success: bool, message: &str, id: &str, version: usize) -> Self {
+        pub fn new(success: bool, message: &str, id: Option<NodeId>, version: usize) -> Self {
             Self {
                 success,
                 message: message.to_owned(),
-                id: id.to_owned(),
+                id,
                 version,
+                unknown_fields: UnknownFields::new(),
             }
         }
 
@@ -622,8 +730,9 @@ pub mod delete {
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, DeleteResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let delete = DeleteResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
         /// assert_eq!(delete.success(), true);
         /// ```
@@ -635,8 +744,9 @@ pub mod delete {
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, DeleteResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let delete = DeleteResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
         /// assert_eq!(delete.success(), true);
         /// ```
@@ -644,31 +754,44 @@ pub mod delete {
             &self.message
         }
 
-        /// Retrieve the ID of an DeleteResponse object
+        /// Retrieve the ID of an DeleteResponse object, if it has one
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, DeleteResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let delete = DeleteResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
-        /// assert_eq!(delete.id(), "some-id");
+        /// assert_eq!(delete.id().unwrap(), "092fd1cc-df38-4fc5-8633-3a8a15a2a316");
         /// ```
-        pub fn id(&self) -> &str {
-            &self.id
+        pub fn id(&self) -> Option<&NodeId> {
+            self.id.as_ref()
         }
 
         /// Retrieve the version of an DeleteResponse object
         ///
         /// ```
         /// # use remsync_api_types::{NodeType, DeleteResponse};
+        /// # let id = "092fd1cc-df38-4fc5-8633-3a8a15a2a316".parse().unwrap();
         /// # let delete = DeleteResponse::new(
-        /// #     true, "", "some-id", 4,
+        /// #     true, "", Some(id), 4,
         /// # );
         /// assert_eq!(delete.version(), 4);
         /// ```
         pub fn version(&self) -> usize {
             self.version
         }
+
+        /// Retrieve the fields of a DeleteResponse which we don't know about
+        pub fn unknown_fields(&self) -> &UnknownFields {
+            &self.unknown_fields
+        }
+    }
+
+    impl ReceivedIds for DeleteResponse {
+        fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+            self.id.check_ids(lenient)
+        }
     }
 }
 
@@ -698,6 +821,14 @@ mod test {
         )
     }
 
+    #[test]
+    fn received_docs_are_checked() {
+        let json = br#"[{"Success":true,"Message":"","ID":"20d581ff-4507-4fc3-a7fb-07daf6c4bc86","Version":1,"BlobURLGet":"","BlobURLGetExpires":"0001-01-01T00:00:00Z","ModifiedClient":"2019-08-30T12:45:51Z","Type":"DocumentType","VissibleName":"Odd","CurrentPage":0,"Bookmarked":false,"Parent":"RM102-928-57210"}]"#;
+        assert!(from_received_slice::<Vec<DocsResponse>>(json, false).is_err());
+        let docs: Vec<DocsResponse> = from_received_slice(json, true).unwrap();
+        assert_eq!(*docs[0].parent(), "RM102-928-57210");
+    }
+
     #[test]
     fn docs_response() {
         // It's useful to note that docs always returns a list, even for a single
@@ -772,6 +903,23 @@ mod test {
         )
     }
 
+    #[test]
+    fn failure_without_id() {
+        let content = r#"
+[
+  {
+    "Success": false,
+    "Message": "No such document",
+    "ID": "",
+    "Version": 0
+  }
+]
+"#;
+        round_trip::<Vec<DeleteResponse>>(content);
+        let replies: Vec<DeleteResponse> = from_str(content.trim()).unwrap();
+        assert_eq!(replies[0].id(), None);
+    }
+
     #[test]
     fn delete_response() {
         round_trip::<Vec<DeleteResponse>>(
@@ -784,6 +932,33 @@ mod test {
     "Version": 3
   }
 ]
+"#,
+        )
+    }
+
+    #[test]
+    fn docs_response_from_the_future() {
+        // Fields and types we don't know about must survive a round trip
+        round_trip::<DocsResponse>(
+            r#"
+{
+  "Success": true,
+  "Message": "",
+  "ID": "20d581ff-4507-4fc3-a7fb-07daf6c4bc86",
+  "Version": 2,
+  "BlobURLGet": "",
+  "BlobURLGetExpires": "0001-01-01T00:00:00Z",
+  "ModifiedClient": "2019-08-31T14:34:43.664664Z",
+  "Type": "TemplateType",
+  "VissibleName": "Dot grid",
+  "CurrentPage": 0,
+  "Bookmarked": false,
+  "Parent": "",
+  "Pinned": true,
+  "Tags": [
+    "work"
+  ]
+}
 "#,
         )
     }
@@ -0,0 +1,317 @@
+//! Timestamps as they appear in the API and on disk
+//!
+//! The cloud API renders times as RFC3339 strings, with the zero time
+//! `0001-01-01T00:00:00Z` standing for "never", while the on-disk metadata
+//! renders them as milliseconds since the epoch in a string.  A
+//! [`Timestamp`] accepts either, keeps the exact text it was given so that
+//! it serialises back byte for byte, and orders by the time it represents.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The text of the zero time, as sent by the API for "never"
+const ZERO: &str = "0001-01-01T00:00:00Z";
+
+/// A point in time, in any of the forms the API and disk formats use
+///
+/// Timestamps compare by the time they represent, and then by their text,
+/// so two renderings of the same instant are ordered but not equal.  An
+/// empty timestamp sorts before all others.
+///
+/// ```
+/// # use remsync_api_types::Timestamp;
+/// let api: Timestamp = "2019-09-01T10:29:26.440Z".parse().unwrap();
+/// let disk: Timestamp = "1567333766441".parse().unwrap();
+/// assert!(disk > api);
+/// assert_eq!(disk.to_string(), "1567333766441");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Timestamp {
+    text: String,
+    time: Option<DateTime<Utc>>,
+}
+
+/// The error returned when a timestamp can't be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimestampError(String);
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unparseable timestamp {:?}", self.0)
+    }
+}
+
+impl std::error::Error for TimestampError {}
+
+impl Timestamp {
+    /// Parse a timestamp in RFC3339 or epoch-milliseconds form
+    ///
+    /// An empty string is accepted as an empty timestamp, which has no time.
+    pub fn parse(text: &str) -> Result<Self, TimestampError> {
+        let time = if text.is_empty() {
+            None
+        } else if text.bytes().all(|b| b.is_ascii_digit()) {
+            let millis: i64 = text.parse().map_err(|_| TimestampError(text.to_owned()))?;
+            Some(
+                Utc.timestamp_millis_opt(millis)
+                    .single()
+                    .ok_or_else(|| TimestampError(text.to_owned()))?,
+            )
+        } else {
+            let time =
+                DateTime::parse_from_rfc3339(text).map_err(|_| TimestampError(text.to_owned()))?;
+            Some(time.with_timezone(&Utc))
+        };
+        Ok(Self {
+            text: text.to_owned(),
+            time,
+        })
+    }
+
+    /// The zero time, which the API uses to mean "never"
+    ///
+    /// ```
+    /// # use remsync_api_types::Timestamp;
+    /// assert!(Timestamp::zero().is_zero());
+    /// assert_eq!(Timestamp::zero(), "0001-01-01T00:00:00Z");
+    /// ```
+    pub fn zero() -> Self {
+        Self::parse(ZERO).expect("The zero time parses")
+    }
+
+    /// A timestamp in RFC3339 form, to the millisecond, as the API uses
+    ///
+    /// ```
+    /// # use remsync_api_types::Timestamp;
+    /// # use chrono::{TimeZone, Utc};
+    /// let time = Utc.timestamp_millis_opt(1_567_333_766_440).unwrap();
+    /// assert_eq!(Timestamp::from_datetime(time), "2019-09-01T10:29:26.440Z");
+    /// ```
+    pub fn from_datetime(time: DateTime<Utc>) -> Self {
+        Self {
+            text: time.to_rfc3339_opts(SecondsFormat::Millis, true),
+            time: Some(time),
+        }
+    }
+
+    /// A timestamp in epoch-milliseconds form, as the disk format uses
+    ///
+    /// ```
+    /// # use remsync_api_types::Timestamp;
+    /// let time = Timestamp::from_epoch_millis(1_567_333_766_440);
+    /// assert_eq!(time, "1567333766440");
+    /// assert_eq!(time.epoch_millis(), Some(1_567_333_766_440));
+    /// ```
+    pub fn from_epoch_millis(millis: u64) -> Self {
+        Self::parse(&millis.to_string()).expect("Epoch milliseconds parse")
+    }
+
+    /// A timestamp in RFC3339 form, to the second, from seconds since the
+    /// epoch as JWTs use
+    ///
+    /// This is `None` if the time is too far in the future to represent.
+    ///
+    /// ```
+    /// # use remsync_api_types::Timestamp;
+    /// let time = Timestamp::from_epoch_secs(1_567_431_013).unwrap();
+    /// assert_eq!(time, "2019-09-02T13:30:13Z");
+    /// ```
+    pub fn from_epoch_secs(secs: u64) -> Option<Self> {
+        let time = Utc.timestamp_opt(i64::try_from(secs).ok()?, 0).single()?;
+        Some(Self {
+            text: time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            time: Some(time),
+        })
+    }
+
+    /// The time now, in RFC3339 form
+    pub fn now() -> Self {
+        Self::from_datetime(Utc::now())
+    }
+
+    /// The time now, in epoch-milliseconds form
+    pub fn now_epoch_millis() -> Self {
+        Self::from_epoch_millis(since_epoch().as_millis() as u64)
+    }
+
+    /// The exact text of the timestamp
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The time represented, unless the timestamp is empty
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        self.time
+    }
+
+    /// The time represented as a `SystemTime`, unless the timestamp is empty
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let time = self.time?;
+        let secs = time.timestamp();
+        let nanos = Duration::from_nanos(u64::from(time.timestamp_subsec_nanos()));
+        if secs >= 0 {
+            Some(UNIX_EPOCH + Duration::from_secs(secs as u64) + nanos)
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+                .map(|t| t + nanos)
+        }
+    }
+
+    /// The time represented in milliseconds since the epoch, unless the
+    /// timestamp is empty
+    pub fn epoch_millis(&self) -> Option<i64> {
+        self.time.map(|time| time.timestamp_millis())
+    }
+
+    /// Whether this is empty or the zero time, both meaning "never"
+    ///
+    /// ```
+    /// # use remsync_api_types::Timestamp;
+    /// assert!(Timestamp::parse("").unwrap().is_zero());
+    /// assert!(!Timestamp::now().is_zero());
+    /// ```
+    pub fn is_zero(&self) -> bool {
+        match self.time {
+            Some(time) => time <= Timestamp::zero().time.expect("The zero time has a time"),
+            None => true,
+        }
+    }
+}
+
+/// The time now, in seconds since the epoch as JWTs use
+///
+/// ```
+/// # use remsync_api_types::{now_epoch_secs, Timestamp};
+/// let now = Timestamp::from_epoch_secs(now_epoch_secs()).unwrap();
+/// assert!(now > "2019-09-01T10:29:26.440Z".parse().unwrap());
+/// ```
+pub fn now_epoch_secs() -> u64 {
+    since_epoch().as_secs()
+}
+
+/// How long it is since the epoch
+fn since_epoch() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("We're before epoch?")
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialEq<str> for Timestamp {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl PartialEq<&str> for Timestamp {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .cmp(&other.time)
+            .then_with(|| self.text.cmp(&other.text))
+    }
+}
+
+impl Hash for Timestamp {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.text)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn forms_round_trip() {
+        for text in &[
+            "2019-08-31T15:52:52.186395996Z",
+            "2019-09-01T10:29:26.440Z",
+            "2019-09-01T12:29:26+02:00",
+            "0001-01-01T00:00:00Z",
+            "1567333766441",
+            "",
+        ] {
+            let json = serde_json::to_string(text).unwrap();
+            let time: Timestamp = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&time).unwrap(), json);
+        }
+        assert!(serde_json::from_str::<Timestamp>("\"yesterday\"").is_err());
+    }
+
+    #[test]
+    fn times_are_compared() {
+        let zero = Timestamp::zero();
+        let api: Timestamp = "2019-09-01T10:29:26.440Z".parse().unwrap();
+        let offset: Timestamp = "2019-09-01T12:29:26.440+02:00".parse().unwrap();
+        let disk = Timestamp::from_epoch_millis(1_567_333_766_440);
+        assert!(zero < api);
+        assert_eq!(api.datetime(), disk.datetime());
+        assert_eq!(api.datetime(), offset.datetime());
+        assert_ne!(api, disk);
+        assert_eq!(
+            api.to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_millis(1_567_333_766_440))
+        );
+        assert!(zero.to_system_time().unwrap() < UNIX_EPOCH);
+    }
+}
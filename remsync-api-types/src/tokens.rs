@@ -1,7 +1,14 @@
 //! Types for the remsync API regarding JWTs in use
 
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::{Timestamp, UnknownFields};
+
+/// The subject of every user token
+const USER_TOKEN_SUBJECT: &str = "rM User Token";
+
 /// A Device JWT's claims
 ///
 /// This is basically the claim structure created/used by the reMarkable
@@ -33,6 +40,9 @@ pub struct DeviceToken {
     #[serde(rename = "sub")]
     /// The subject of this token, always `rM Device Token`
     subject: String,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
 }
 
 impl DeviceToken {
@@ -45,6 +55,7 @@ impl DeviceToken {
     ///     123456, "some-issuer", "some-token-id", 4321, "some-subject"
     /// );
     /// ```
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         auth0_user_id: &str,
         device_desc: &str,
@@ -64,6 +75,7 @@ impl DeviceToken {
             token_identity: token_identity.to_owned(),
             not_valid_before,
             subject: subject.to_owned(),
+            unknown_fields: UnknownFields::new(),
         }
     }
 
@@ -177,6 +189,11 @@ impl DeviceToken {
     pub fn subject(&self) -> &str {
         &self.subject
     }
+
+    /// Retrieve the fields of a DeviceToken which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
 }
 
 /// User profile used as part of a UserToken JWT claim (see below)
@@ -194,7 +211,7 @@ pub struct Auth0Profile {
     connection: String,
     #[serde(rename = "CreatedAt")]
     /// When this profile was created
-    created_at: String,
+    created_at: Timestamp,
     #[serde(rename = "Email")]
     /// The email address for this user
     email: String,
@@ -224,21 +241,326 @@ pub struct Auth0Profile {
     picture: String,
     #[serde(rename = "UpdatedAt")]
     /// When this profile was last updated
-    updated_at: String,
+    updated_at: Timestamp,
     #[serde(rename = "UserID")]
     /// The userid for this profile (MOST IMPORTANT)
     user_id: String,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
 }
 
 impl Auth0Profile {
-    // TODO: Finish this API
+    /// Create a new Auth0Profile for the given user
+    ///
+    /// Everything else in the profile is blank, and it has never been
+    /// updated.  Use the `with_*` methods to fill it in.
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// let profile = Auth0Profile::new(
+    ///     "auth0|5d6b9d86fe3c560e1e4da801",
+    ///     "someone@example.com",
+    ///     "2019-09-01T10:29:26.440Z".parse().unwrap(),
+    /// )
+    /// .with_email_verified(true);
+    /// assert_eq!(profile.name(), "");
+    /// assert_eq!(profile.nickname(), "");
+    /// assert!(profile.updated_at().is_zero());
+    /// assert!(profile.email_verified());
+    /// ```
+    pub fn new(user_id: &str, email: &str, created_at: Timestamp) -> Self {
+        Self {
+            client_id: String::new(),
+            connection: String::new(),
+            updated_at: Timestamp::zero(),
+            created_at,
+            email: email.to_owned(),
+            email_verified: false,
+            family_name: String::new(),
+            given_name: String::new(),
+            is_social: false,
+            locale: String::new(),
+            name: String::new(),
+            nickname: String::new(),
+            picture: String::new(),
+            user_id: user_id.to_owned(),
+            unknown_fields: UnknownFields::new(),
+        }
+    }
+
+    /// Set whether the email address was verified
+    pub fn with_email_verified(mut self, email_verified: bool) -> Self {
+        self.email_verified = email_verified;
+        self
+    }
+
+    /// Set the user's family name
+    pub fn with_family_name(mut self, family_name: &str) -> Self {
+        self.family_name = family_name.to_owned();
+        self
+    }
+
+    /// Set the user's given name
+    pub fn with_given_name(mut self, given_name: &str) -> Self {
+        self.given_name = given_name.to_owned();
+        self
+    }
+
+    /// Set whether the account is via social media of some kind
+    pub fn with_social(mut self, is_social: bool) -> Self {
+        self.is_social = is_social;
+        self
+    }
+
+    /// Set the user's locale
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        self.locale = locale.to_owned();
+        self
+    }
+
+    /// Set the user's name
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Set the user's nickname
+    pub fn with_nickname(mut self, nickname: &str) -> Self {
+        self.nickname = nickname.to_owned();
+        self
+    }
+
+    /// Set the URL of the user's picture
+    pub fn with_picture(mut self, picture: &str) -> Self {
+        self.picture = picture.to_owned();
+        self
+    }
+
+    /// Set when the profile was last updated
+    pub fn with_updated_at(mut self, updated_at: Timestamp) -> Self {
+        self.updated_at = updated_at;
+        self
+    }
+
+    /// Retrieve the client ID from the given profile
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.client_id(), "");
+    /// ```
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Retrieve the connection from the given profile
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.connection(), "");
+    /// ```
+    pub fn connection(&self) -> &str {
+        &self.connection
+    }
+
+    /// Retrieve when the given profile was created
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(*profile.created_at(), "2019-09-01T10:29:26.440Z");
+    /// ```
+    pub fn created_at(&self) -> &Timestamp {
+        &self.created_at
+    }
+
+    /// Retrieve the email address from the given profile
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.email(), "someone@example.com");
+    /// ```
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    /// Retrieve whether the email address of the given profile was verified
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert!(!profile.email_verified());
+    /// assert!(profile.with_email_verified(true).email_verified());
+    /// ```
+    pub fn email_verified(&self) -> bool {
+        self.email_verified
+    }
+
+    /// Retrieve the family name from the given profile (may be blank)
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.family_name(), "");
+    /// let profile = profile.with_family_name("Smith");
+    /// assert_eq!(profile.family_name(), "Smith");
+    /// ```
+    pub fn family_name(&self) -> &str {
+        &self.family_name
+    }
+
+    /// Retrieve the given name from the given profile (may be blank)
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.given_name(), "");
+    /// let profile = profile.with_given_name("Alex");
+    /// assert_eq!(profile.given_name(), "Alex");
+    /// ```
+    pub fn given_name(&self) -> &str {
+        &self.given_name
+    }
+
+    /// Retrieve whether the given profile is via social media of some kind
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert!(!profile.is_social());
+    /// assert!(profile.with_social(true).is_social());
+    /// ```
+    pub fn is_social(&self) -> bool {
+        self.is_social
+    }
+
+    /// Retrieve the locale from the given profile (may be blank)
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.locale(), "");
+    /// let profile = profile.with_locale("en_GB");
+    /// assert_eq!(profile.locale(), "en_GB");
+    /// ```
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Retrieve the name from the given profile
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.name(), "");
+    /// let profile = profile.with_name("Someone");
+    /// assert_eq!(profile.name(), "Someone");
+    /// ```
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Retrieve the nickname from the given profile
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.nickname(), "");
+    /// let profile = profile.with_nickname("some1");
+    /// assert_eq!(profile.nickname(), "some1");
+    /// ```
+    pub fn nickname(&self) -> &str {
+        &self.nickname
+    }
+
+    /// Retrieve the picture URL from the given profile
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.picture(), "");
+    /// let profile = profile.with_picture("https://example.com/me.png");
+    /// assert_eq!(profile.picture(), "https://example.com/me.png");
+    /// ```
+    pub fn picture(&self) -> &str {
+        &self.picture
+    }
+
+    /// Retrieve when the given profile was last updated
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert!(profile.updated_at().is_zero());
+    /// let profile = profile.with_updated_at("2019-09-01T10:31:21.678Z".parse().unwrap());
+    /// assert_eq!(*profile.updated_at(), "2019-09-01T10:31:21.678Z");
+    /// ```
+    pub fn updated_at(&self) -> &Timestamp {
+        &self.updated_at
+    }
 
     /// Retrieve the user id from the given profile
     ///
     /// This is usually of the form "auth0|BUNCHOFHEXDIGITS"
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com",
+    /// #     "2019-09-01T10:29:26.440Z".parse().unwrap()
+    /// # );
+    /// assert_eq!(profile.user_id(), "some-user-id");
+    /// ```
     pub fn user_id(&self) -> &str {
         &self.user_id
     }
+
+    /// Retrieve the fields of an Auth0Profile which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
 }
 
 /// User token used as claims in JWT in reMarkable API
@@ -275,20 +597,290 @@ pub struct UserToken {
     #[serde(rename = "sub")]
     /// The subject of this token, always `rM User Token`
     subject: String,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
 }
 
 impl UserToken {
-    // TODO: Complete this API
+    /// Create a new UserToken to be used in the claims of the JWT
+    ///
+    /// The token is valid from when it is issued, and its subject is
+    /// `rM User Token`.
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// let claims = UserToken::new(
+    ///     profile, "some-device-desc", "some-device-id",
+    ///     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// );
+    /// assert_eq!(claims.not_valid_before(), 123456);
+    /// assert_eq!(claims.expires_at(), 123456 + 86400);
+    /// assert_eq!(claims.subject(), "rM User Token");
+    /// ```
+    pub fn new(
+        auth0_profile: Auth0Profile,
+        device_desc: &str,
+        device_id: &str,
+        issued_at: u64,
+        lifetime: Duration,
+        issuer: &str,
+        token_identity: &str,
+    ) -> Self {
+        Self {
+            auth0_profile,
+            device_desc: device_desc.to_owned(),
+            device_id: device_id.to_owned(),
+            expires_at: issued_at.saturating_add(lifetime.as_secs()),
+            issued_at,
+            issuer: issuer.to_owned(),
+            token_identity: token_identity.to_owned(),
+            not_valid_before: issued_at,
+            subject: USER_TOKEN_SUBJECT.to_owned(),
+            unknown_fields: UnknownFields::new(),
+        }
+    }
+
+    /// Set the time before which this user token is not valid
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// let claims = claims.with_not_valid_before(123000);
+    /// assert_eq!(claims.not_valid_before(), 123000);
+    /// ```
+    pub fn with_not_valid_before(mut self, not_valid_before: u64) -> Self {
+        self.not_valid_before = not_valid_before;
+        self
+    }
+
     /// Retrieve the auth0 information from this user token
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.auth0_profile().user_id(), "some-user-id");
+    /// ```
     pub fn auth0_profile(&self) -> &Auth0Profile {
         &self.auth0_profile
     }
+
+    /// Retrieve the device descriptor from this user token
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.device_desc(), "some-device-desc");
+    /// ```
+    pub fn device_desc(&self) -> &str {
+        &self.device_desc
+    }
+
+    /// Retrieve the device id from this user token
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.device_id(), "some-device-id");
+    /// ```
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// Retrieve the time (seconds since the epoch) when this user token expires
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.expires_at(), 123456 + 86400);
+    /// ```
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    /// Retrieve the time (seconds since the epoch) when this user token was issued
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.issued_at(), 123456);
+    /// ```
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    /// Retrieve the issuer of this user token
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.issuer(), "some-issuer");
+    /// ```
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Retrieve the ID of this user token
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.token_identity(), "some-token-id");
+    /// ```
+    pub fn token_identity(&self) -> &str {
+        &self.token_identity
+    }
+
+    /// Retrieve the time (seconds since the epoch) before which this user
+    /// token is not valid
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.not_valid_before(), 123456);
+    /// ```
+    pub fn not_valid_before(&self) -> u64 {
+        self.not_valid_before
+    }
+
+    /// Retrieve the subject of this user token
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// # let claims = UserToken::new(
+    /// #     profile, "some-device-desc", "some-device-id",
+    /// #     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// # );
+    /// assert_eq!(claims.subject(), "rM User Token");
+    /// ```
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Whether this user token has expired at `now` (seconds since the epoch)
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// let claims = UserToken::new(
+    ///     profile, "some-device-desc", "some-device-id",
+    ///     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// );
+    /// assert!(!claims.is_expired(123456 + 86399));
+    /// assert!(claims.is_expired(123456 + 86400));
+    /// ```
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// How much longer this user token is valid for at `now` (seconds since
+    /// the epoch)
+    ///
+    /// This is zero once the token has expired.
+    ///
+    /// ```
+    /// # use remsync_api_types::*;
+    /// # use std::time::Duration;
+    /// # let profile = Auth0Profile::new(
+    /// #     "some-user-id", "someone@example.com", Timestamp::now()
+    /// # );
+    /// let claims = UserToken::new(
+    ///     profile, "some-device-desc", "some-device-id",
+    ///     123456, Duration::from_secs(86400), "some-issuer", "some-token-id"
+    /// );
+    /// assert_eq!(claims.remaining_validity(123456), Duration::from_secs(86400));
+    /// assert_eq!(claims.remaining_validity(123456 + 86399), Duration::from_secs(1));
+    /// assert_eq!(claims.remaining_validity(123456 + 86400), Duration::from_secs(0));
+    /// ```
+    pub fn remaining_validity(&self, now: u64) -> Duration {
+        Duration::from_secs(self.expires_at.saturating_sub(now))
+    }
+
+    /// Retrieve the fields of a UserToken which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::*;
     use serde_json::{from_str, to_string_pretty};
+    use std::time::Duration;
 
     fn round_trip<'de, T>(content: &'de str)
     where
@@ -349,4 +941,65 @@ mod test {
 "#
         );
     }
+
+    #[test]
+    fn user_token_from_parts() {
+        let profile = Auth0Profile::new(
+            "auth0|5d6b9d86fe3c560e1e4da801",
+            "dsilvers+devremarkable@digital-scurf.org",
+            "2019-09-01T10:29:26.440Z".parse().unwrap(),
+        )
+        .with_email_verified(true)
+        .with_name("dsilvers+devremarkable@digital-scurf.org")
+        .with_nickname("dsilvers+devremarkable")
+        .with_picture("https://s.gravatar.com/avatar/2ccb7db4162bf759176024ac4ef66e08?s=480&r=pg&d=https%3A%2F%2Fcdn.auth0.com%2Favatars%2Fds.png")
+        .with_updated_at("2019-09-01T10:31:21.678Z".parse().unwrap());
+        let claims = UserToken::new(
+            profile,
+            "desktop-linux",
+            "c81d2351-4723-4cfc-93ac-b032d3a053e7",
+            1567344613,
+            Duration::from_secs(86400),
+            "rM WebApp",
+            "ck0tCMsf9jQ=",
+        );
+        let expected = r#"
+{
+  "auth0-profile": {
+    "ClientID": "",
+    "Connection": "",
+    "CreatedAt": "2019-09-01T10:29:26.440Z",
+    "Email": "dsilvers+devremarkable@digital-scurf.org",
+    "EmailVerified": true,
+    "FamilyName": "",
+    "GivenName": "",
+    "IsSocial": false,
+    "Locale": "",
+    "Name": "dsilvers+devremarkable@digital-scurf.org",
+    "Nickname": "dsilvers+devremarkable",
+    "Picture": "https://s.gravatar.com/avatar/2ccb7db4162bf759176024ac4ef66e08?s=480&r=pg&d=https%3A%2F%2Fcdn.auth0.com%2Favatars%2Fds.png",
+    "UpdatedAt": "2019-09-01T10:31:21.678Z",
+    "UserID": "auth0|5d6b9d86fe3c560e1e4da801"
+  },
+  "device-desc": "desktop-linux",
+  "device-id": "c81d2351-4723-4cfc-93ac-b032d3a053e7",
+  "exp": 1567431013,
+  "iat": 1567344613,
+  "iss": "rM WebApp",
+  "jti": "ck0tCMsf9jQ=",
+  "nbf": 1567344613,
+  "sub": "rM User Token"
+}
+"#;
+        assert_eq!(to_string_pretty(&claims).unwrap(), expected.trim());
+        assert!(claims.is_expired(1567431013));
+        assert_eq!(
+            claims.remaining_validity(1567344613 + 3600),
+            Duration::from_secs(86400 - 3600)
+        );
+        assert_eq!(
+            claims.remaining_validity(1567431013),
+            Duration::from_secs(0)
+        );
+    }
 }
@@ -1,13 +1,16 @@
 //! Types for the local disk format (see on-disk.md)
 
-use remsync_api_types::NodeType;
+use remsync_api_types::{
+    NodeId, NodeIdError, NodeType, ParentRef, ReceivedIds, Timestamp, UnknownFields,
+};
 use serde::{Deserialize, Serialize};
 
 /// Metadata held locally for a node
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MetadataFile {
-    /// The parent of the node, or "" for a top-level node
-    parent: String,
+    /// The parent of the node
+    #[serde(deserialize_with = "remsync_api_types::unchecked::parent")]
+    parent: ParentRef,
     /// The type of this node
     #[serde(rename = "type")]
     node_type: NodeType,
@@ -25,7 +28,7 @@ pub struct MetadataFile {
     /// This is represented as a time_t in millisecond precision,
     /// rendered as a string.
     #[serde(rename = "lastModified")]
-    last_modified: String,
+    last_modified: Timestamp,
     /// Whether this node has had its metadata changed since last sync
     #[serde(rename = "metadatamodified")]
     metadata_modified: bool,
@@ -33,6 +36,9 @@ pub struct MetadataFile {
     modified: bool,
     /// Whether this node was deleted since last sync
     deleted: bool,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
 }
 
 impl MetadataFile {
@@ -41,34 +47,35 @@ impl MetadataFile {
     /// A new instance sets only the node type, parent, and name.
     /// Everything else is set as though the node were just created
     /// on a client. This means that it's ready to be sync'd.
-    pub fn new(node_type: NodeType, parent: &str, name: &str) -> Self {
+    pub fn new(node_type: NodeType, parent: ParentRef, name: &str) -> Self {
         Self {
-            parent: parent.to_owned(),
+            parent,
             node_type,
             version: 0, // Zero until synced for the first time
             name: name.to_owned(),
             bookmarked: false,
             synced: false,
-            last_modified: MetadataFile::get_now(),
+            last_modified: Timestamp::now_epoch_millis(),
             metadata_modified: false,
             modified: false,
             deleted: false,
+            unknown_fields: UnknownFields::new(),
         }
     }
 
-    pub fn parent(&self) -> &str {
+    pub fn parent(&self) -> &ParentRef {
         &self.parent
     }
 
-    pub fn set_parent(&mut self, parent: &str) {
-        if self.parent != parent {
-            self.parent = parent.to_owned();
+    pub fn set_parent(&mut self, parent: &ParentRef) {
+        if self.parent != *parent {
+            self.parent = parent.clone();
             self.set_metadata_modified();
         }
     }
 
-    pub fn node_type(&self) -> NodeType {
-        self.node_type
+    pub fn node_type(&self) -> &NodeType {
+        &self.node_type
     }
 
     pub fn version(&self) -> usize {
@@ -101,7 +108,7 @@ impl MetadataFile {
         self.synced
     }
 
-    pub fn last_modified(&self) -> &str {
+    pub fn last_modified(&self) -> &Timestamp {
         &self.last_modified
     }
 
@@ -126,22 +133,23 @@ impl MetadataFile {
 
     pub fn set_modified(&mut self) {
         self.modified = true;
-        self.last_modified = MetadataFile::get_now();
+        self.last_modified = Timestamp::now_epoch_millis();
     }
 
     fn set_metadata_modified(&mut self) {
         self.metadata_modified = true;
-        self.last_modified = MetadataFile::get_now();
+        self.last_modified = Timestamp::now_epoch_millis();
     }
 
-    /// Get now, as a time_t in millisecond precision, as a string
-    fn get_now() -> String {
-        use std::time;
-        let now = time::SystemTime::now()
-            .duration_since(time::UNIX_EPOCH)
-            .expect("We're before epoch?")
-            .as_millis();
-        format!("{}", now)
+    /// Retrieve the fields of a MetadataFile which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
+}
+
+impl ReceivedIds for MetadataFile {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.parent.check_ids(lenient)
     }
 }
 
@@ -151,20 +159,48 @@ pub struct ContentFile {
     /// The number of pages in the document
     #[serde(rename = "pageCount")]
     page_count: usize,
+    /// The IDs of the pages, in order, for documents which have them
+    #[serde(
+        default,
+        deserialize_with = "remsync_api_types::unchecked::node_ids",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pages: Option<Vec<NodeId>>,
+    #[serde(flatten)]
+    /// Any fields we don't know about, kept so they aren't lost
+    unknown_fields: UnknownFields,
 }
 
 impl ContentFile {
     pub fn page_count(&self) -> usize {
         self.page_count
     }
+
+    /// The IDs of the pages, which are empty if the document doesn't list
+    /// them
+    pub fn pages(&self) -> &[NodeId] {
+        self.pages.as_deref().unwrap_or_default()
+    }
+
+    /// Retrieve the fields of a ContentFile which we don't know about
+    pub fn unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
+}
+
+impl ReceivedIds for ContentFile {
+    fn check_ids(&self, lenient: bool) -> Result<(), NodeIdError> {
+        self.pages.check_ids(lenient)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use remsync_api_types::from_received_slice;
     use serde_json::{from_str, from_value, to_value, Value};
 
-    fn round_trip<'de, T>(content: &'de str)
+    fn round_trip<T>(content: &str)
     where
         T: serde::Serialize + serde::de::DeserializeOwned,
     {
@@ -193,4 +229,35 @@ mod test {
 "#,
         )
     }
+
+    #[test]
+    fn content() {
+        round_trip::<ContentFile>(
+            r#"
+{
+  "extraMetadata": {},
+  "fileType": "notebook",
+  "orientation": "portrait",
+  "pageCount": 3,
+  "pages": [
+    "0a0c5b3b-1c3e-4b8e-a8c1-56f4c1a2fe2b"
+  ]
+}
+"#,
+        )
+    }
+
+    #[test]
+    fn page_ids_are_checked() {
+        let json = br#"{"pageCount": 2, "pages": ["0a0c5b3b-1c3e-4b8e-a8c1-56f4c1a2fe2b", "p2"]}"#;
+        assert!(from_received_slice::<ContentFile>(json, false).is_err());
+        let content: ContentFile = from_received_slice(json, true).unwrap();
+        assert_eq!(content.pages()[1], "p2");
+        let content: ContentFile = from_received_slice(br#"{"pageCount": 0}"#, false).unwrap();
+        assert!(content.pages().is_empty());
+        assert_eq!(
+            to_value(content).unwrap(),
+            from_str::<Value>(r#"{"pageCount": 0}"#).unwrap()
+        );
+    }
 }
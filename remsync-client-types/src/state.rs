@@ -3,7 +3,7 @@
 use std::path::{Path, PathBuf};
 // TODO: Do a better job of error handling
 use crate::local::MetadataFile;
-use serde_json::from_reader;
+use remsync_api_types::{from_received_reader, NodeId};
 use std::collections::HashMap;
 use std::error::Error;
 
@@ -11,13 +11,17 @@ use std::error::Error;
 #[derive(Debug)]
 pub struct ClientState {
     base_path: PathBuf,
-    nodes: HashMap<String, MetadataFile>,
+    lenient_ids: bool,
+    nodes: HashMap<NodeId, MetadataFile>,
 }
 
 impl ClientState {
-    pub fn new<P: AsRef<Path>>(base_path: P) -> Result<Self, Box<dyn Error>> {
+    /// Load the state in `base_path`, accepting IDs which are not UUIDs
+    /// if `lenient_ids` is set
+    pub fn new<P: AsRef<Path>>(base_path: P, lenient_ids: bool) -> Result<Self, Box<dyn Error>> {
         let mut ret = Self {
             base_path: base_path.as_ref().to_owned(),
+            lenient_ids,
             nodes: HashMap::new(),
         };
 
@@ -32,18 +36,19 @@ impl ClientState {
             let full_path = entry.path();
             let basename = full_path.file_stem().ok_or("No file stem?")?;
             let basename = basename.to_str().ok_or("Odd, UUIDs are ASCII")?;
-            let node_id = basename.to_owned();
-            let metadata: MetadataFile = from_reader(std::fs::File::open(full_path)?)?;
+            let node_id = NodeId::parse_received(basename, self.lenient_ids)?;
+            let metadata: MetadataFile =
+                from_received_reader(std::fs::File::open(full_path)?, self.lenient_ids)?;
             self.nodes.insert(node_id, metadata);
         }
         Ok(())
     }
 
-    pub fn node_metadata(&self, node: &str) -> Option<&MetadataFile> {
+    pub fn node_metadata(&self, node: &NodeId) -> Option<&MetadataFile> {
         self.nodes.get(node)
     }
 
-    pub fn node_metadata_mut(&mut self, node: &str) -> Option<&mut MetadataFile> {
+    pub fn node_metadata_mut(&mut self, node: &NodeId) -> Option<&mut MetadataFile> {
         self.nodes.get_mut(node)
     }
 }
@@ -6,21 +6,20 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "remsync", about = "reMarkable sync tool")]
 pub struct Options {
-    #[structopt(
-        long = "auth-server",
-        name = "authentication server",
-        default_value = "https://my.remarkable.com/"
-    )]
+    #[structopt(long = "profile", name = "profile", env = "REMSYNC_PROFILE")]
+    /// The deployment to talk to: production, staging, development, qa,
+    /// self-hosted or self-hosted:<domain> (defaults to production)
+    pub profile: Option<String>,
+
+    #[structopt(long = "auth-server", name = "authentication server")]
     /// Authentication server to use to acquire bearer tokens
-    pub auth_server: String,
+    /// (overrides the profile)
+    pub auth_server: Option<String>,
 
-    #[structopt(
-        long = "discovery-server",
-        name = "discovery server",
-        default_value = "https://service-manager-production-dot-remarkable-production.appspot.com/"
-    )]
+    #[structopt(long = "discovery-server", name = "discovery server")]
     /// Discovery server to use to find the various services
-    pub discovery_server: String,
+    /// (overrides the profile)
+    pub discovery_server: Option<String>,
 
     #[structopt(
         long = "device-token",
@@ -30,6 +29,69 @@ pub struct Options {
     )]
     pub device_token: String,
 
+    #[structopt(
+        long = "token-cache",
+        name = "token cache",
+        env = "REMSYNC_TOKEN_CACHE",
+        parse(from_os_str)
+    )]
+    /// File in which to cache user tokens between runs
+    /// (defaults to remsync/user-tokens.json in the XDG cache directory)
+    pub token_cache: Option<PathBuf>,
+
+    #[structopt(long = "no-token-cache")]
+    /// Do not cache user tokens on disk
+    pub no_token_cache: bool,
+
+    #[structopt(
+        long = "config",
+        name = "config file",
+        env = "REMSYNC_CONFIG",
+        parse(from_os_str)
+    )]
+    /// Configuration file to read
+    /// (defaults to remsync/config.json in the XDG config directory)
+    pub config: Option<PathBuf>,
+
+    #[structopt(long = "proxy", name = "proxy")]
+    /// HTTP proxy through which to tunnel HTTPS connections
+    /// (overrides HTTPS_PROXY, use "" for none)
+    pub proxy: Option<String>,
+
+    #[structopt(long = "no-proxy", name = "hosts")]
+    /// Comma separated hosts to connect to without the proxy
+    /// (overrides NO_PROXY)
+    pub no_proxy: Option<String>,
+
+    #[structopt(
+        long = "ca-cert",
+        name = "PEM file",
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    /// Extra certificate authorities to trust (may be repeated)
+    pub ca_certs: Vec<PathBuf>,
+
+    #[structopt(long = "pin-cert", name = "SHA-256 fingerprint", number_of_values = 1)]
+    /// Only accept servers with this certificate (may be repeated)
+    pub pinned_certs: Vec<String>,
+
+    #[structopt(long = "record", name = "recording", parse(from_os_str))]
+    /// Record all traffic, with credentials redacted, to this JSON-lines file
+    pub record: Option<PathBuf>,
+
+    #[structopt(long = "verify-tokens", name = "JWKS file", parse(from_os_str))]
+    /// Verify device and user tokens against the keys in this JWKS file
+    pub token_jwks: Option<PathBuf>,
+
+    #[structopt(long = "lenient-ids")]
+    /// Accept node IDs from the server which are not UUIDs
+    pub lenient_ids: bool,
+
+    #[structopt(long = "retries", name = "retries", default_value = "3")]
+    /// How many times to retry requests which fail transiently
+    pub retries: u32,
+
     #[structopt(subcommand)]
     /// The command selected by the user
     pub cmd: Command,
@@ -57,7 +119,11 @@ pub enum Command {
     },
     #[structopt(name = "show-tokens")]
     /// Show the content of the device and user tokens
-    ShowTokens,
+    ShowTokens {
+        /// Print the raw claims of the tokens rather than a summary
+        #[structopt(long = "raw")]
+        raw: bool,
+    },
     #[structopt(name = "ls")]
     /// List the contents of the server
     ListServer,
@@ -69,12 +135,44 @@ pub enum Command {
         /// Output location for the blob
         out: PathBuf,
     },
+    #[structopt(name = "mv")]
+    /// Move a node into another collection
+    Move {
+        /// The ID of the node to move
+        id: String,
+        /// The ID of the collection to move it into
+        /// ("" for the top level, "trash" for the trash)
+        parent: String,
+    },
+    #[structopt(name = "rename")]
+    /// Rename a node
+    Rename {
+        /// The ID of the node to rename
+        id: String,
+        /// The new name for the node
+        name: String,
+    },
+    #[structopt(name = "pin")]
+    /// Bookmark a node
+    Pin {
+        /// The ID of the node to bookmark
+        id: String,
+    },
+    #[structopt(name = "unpin")]
+    /// Remove the bookmark from a node
+    Unpin {
+        /// The ID of the node to unbookmark
+        id: String,
+    },
     #[structopt(name = "server-pull")]
     /// Synchronise a directory to match server content
     ServerPull {
         #[structopt(name = "dir")]
         /// The directory to sync with
         basepath: PathBuf,
+        #[structopt(long = "jobs", short = "j", default_value = "4")]
+        /// How many blobs to transfer at once
+        jobs: usize,
     },
     #[structopt(name = "server-push")]
     /// Synchronise a server to match directory content
@@ -82,5 +180,8 @@ pub enum Command {
         #[structopt(name = "dir")]
         /// The directory to sync with
         basepath: PathBuf,
+        #[structopt(long = "jobs", short = "j", default_value = "4")]
+        /// How many blobs to transfer at once
+        jobs: usize,
     },
 }
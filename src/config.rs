@@ -0,0 +1,77 @@
+//! The remsync configuration file
+//!
+//! Settings which are tedious to give on every command line can be put in
+//! a JSON configuration file instead.  Options given on the command line
+//! take precedence over the file.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::Result;
+use crate::cli::Options;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The deployment to talk to, see `--profile`
+    pub profile: Option<String>,
+    /// HTTP proxy through which to tunnel HTTPS connections
+    pub proxy: Option<String>,
+    /// Comma separated hosts to connect to without the proxy
+    pub no_proxy: Option<String>,
+    /// PEM files of extra certificate authorities to trust, relative to
+    /// the configuration file
+    pub ca_certs: Vec<PathBuf>,
+    /// SHA-256 fingerprints of the only server certificates to accept
+    pub pinned_certs: Vec<String>,
+    /// JWKS file of keys to verify tokens against, relative to the
+    /// configuration file
+    pub token_jwks: Option<PathBuf>,
+    /// Accept node IDs from the server which are not UUIDs
+    pub lenient_ids: bool,
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let mut path = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let mut home = PathBuf::from(std::env::var_os("HOME")?);
+            home.push(".config");
+            home
+        }
+    };
+    path.push("remsync");
+    path.push("config.json");
+    Some(path)
+}
+
+impl Config {
+    /// Load the configuration file named in `opt`, or the default one
+    ///
+    /// It's fine for the default configuration file not to exist.
+    pub fn load(opt: &Options) -> Result<Config> {
+        let (path, required) = match &opt.config {
+            Some(path) => (path.clone(), true),
+            None => match default_config_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Config::default())
+            }
+            Err(e) => return Err(format!("Unable to read {}: {}", path.display(), e).into()),
+        };
+        let mut config: Config = serde_json::from_slice(&content)
+            .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        config.ca_certs = config.ca_certs.iter().map(|p| base.join(p)).collect();
+        config.token_jwks = config.token_jwks.map(|p| base.join(p));
+        Ok(config)
+    }
+}
@@ -1,21 +1,91 @@
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 mod cli;
+mod config;
 use cli::{Command, Options};
+use config::Config;
+use remsync_api_client::connector::{Connector, ConnectorConfig};
 use remsync_api_client::hyper::{self, Uri};
+use remsync_api_client::jwt::TokenVerifier;
 use remsync_api_client::ll as llapi;
+use remsync_api_client::recording::Recorder;
+use remsync_api_client::{
+    Backoff, Profile, RetryPolicy, StorageSession, TokenCache, Transfer, TransferScheduler,
+    Transport,
+};
 use remsync_api_types as api;
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
 fn random_uuid() -> String {
     format!("{}", uuid::Uuid::new_v4())
 }
 
-fn https_capable_client(
-) -> hyper::Client<hyper_tls::HttpsConnector<hyper::client::connect::HttpConnector>> {
-    let connector = hyper_tls::HttpsConnector::new().expect("Unable to construct connector");
-    hyper::Client::builder().build(connector)
+/// Whether node IDs need not be UUIDs
+fn lenient_ids(opt: &Options, config: &Config) -> bool {
+    opt.lenient_ids || config.lenient_ids
+}
+
+/// Parse a node ID given on the command line
+fn node_id(id: &str, lenient: bool) -> Result<api::NodeId> {
+    Ok(api::NodeId::parse_received(id, lenient)?)
+}
+
+/// Parse a parent given on the command line
+fn parent_ref(parent: &str, lenient: bool) -> Result<api::ParentRef> {
+    Ok(api::ParentRef::parse_received(parent, lenient)?)
+}
+
+fn profile(opt: &Options, config: &Config) -> Result<Profile> {
+    let name = opt
+        .profile
+        .as_ref()
+        .or(config.profile.as_ref())
+        .map(String::as_str)
+        .unwrap_or("production");
+    let mut profile = Profile::named(name)?;
+    if let Some(auth_server) = &opt.auth_server {
+        profile = profile.with_auth_base(Uri::from_str(auth_server)?);
+    }
+    if let Some(discovery_server) = &opt.discovery_server {
+        profile = profile.with_discovery_base(Uri::from_str(discovery_server)?);
+    }
+    Ok(profile)
+}
+
+fn connector(opt: &Options, config: &Config) -> Result<Connector> {
+    let mut net = ConnectorConfig::from_env()?;
+    match opt.proxy.as_ref().or(config.proxy.as_ref()) {
+        Some(proxy) if proxy.is_empty() => net = net.without_proxy(),
+        Some(proxy) => net = net.with_proxy(proxy)?,
+        None => {}
+    }
+    if let Some(no_proxy) = opt.no_proxy.as_ref().or(config.no_proxy.as_ref()) {
+        net = net.with_no_proxy(no_proxy);
+    }
+    for path in config.ca_certs.iter().chain(&opt.ca_certs) {
+        let pem =
+            std::fs::read(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        net = net.with_root_certificates(&pem)?;
+    }
+    for fingerprint in config.pinned_certs.iter().chain(&opt.pinned_certs) {
+        net = net.with_pinned_certificate(fingerprint)?;
+    }
+    Ok(net.build()?)
+}
+
+fn https_capable_client(opt: &Options, config: &Config) -> Result<hyper::Client<Connector>> {
+    Ok(hyper::Client::builder().build(connector(opt, config)?))
+}
+
+fn transport(opt: &Options, config: &Config) -> Result<Box<dyn Transport>> {
+    let client = https_capable_client(opt, config)?;
+    match opt.record {
+        Some(ref path) => Ok(Box::new(Recorder::create(client, path)?)),
+        None => Ok(Box::new(client)),
+    }
 }
 
 fn render_token(token: &str) -> Result<String> {
@@ -23,15 +93,109 @@ fn render_token(token: &str) -> Result<String> {
     Ok(format!("{:#}", jwt.claims))
 }
 
-async fn discover_storage_base(opt: &Options, user_token: &str) -> Result<hyper::Uri> {
-    let base_uri = Uri::from_str(&opt.discovery_server)?;
-    let client = https_capable_client();
-    let host = llapi::discover_storage_service(&client, &base_uri, user_token).await?;
-    Ok(Uri::from_str(&format!("https://{}/", host))?)
+/// Render a JWT time (seconds since the epoch) for people
+fn render_time(secs: u64) -> String {
+    api::Timestamp::from_epoch_secs(secs).map_or_else(|| secs.to_string(), |t| t.to_string())
+}
+
+/// Render a length of time for people, to the minute
+fn render_duration(duration: std::time::Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, m) => format!("{}d {}h {}m", d, h, m),
+    }
+}
+
+fn summarise_device_token(token: &str) -> Result<String> {
+    let claims = jsonwebtoken::dangerous_unsafe_decode::<api::DeviceToken>(token)?.claims;
+    Ok(format!(
+        "  User:    {}\n  Device:  {} ({})\n  Issued:  {} by {}",
+        claims.auth0_user_id(),
+        claims.device_desc(),
+        claims.device_id(),
+        render_time(claims.issued_at()),
+        claims.issuer(),
+    ))
+}
+
+fn summarise_user_token(token: &str) -> Result<String> {
+    let claims = jsonwebtoken::dangerous_unsafe_decode::<api::UserToken>(token)?.claims;
+    let profile = claims.auth0_profile();
+    let remaining = claims.remaining_validity(api::now_epoch_secs());
+    let validity = if remaining.as_secs() == 0 {
+        "expired".to_owned()
+    } else {
+        format!("in {}", render_duration(remaining))
+    };
+    Ok(format!(
+        "  User:    {} <{}>\n  Device:  {} ({})\n  Issued:  {} by {}\n  Expires: {} ({})",
+        profile.user_id(),
+        profile.email(),
+        claims.device_desc(),
+        claims.device_id(),
+        render_time(claims.issued_at()),
+        claims.issuer(),
+        render_time(claims.expires_at()),
+        validity,
+    ))
+}
+
+type Session = StorageSession<Box<dyn Transport>>;
+
+fn default_token_cache_path() -> Option<PathBuf> {
+    let mut path = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let mut home = PathBuf::from(std::env::var_os("HOME")?);
+            home.push(".cache");
+            home
+        }
+    };
+    path.push("remsync");
+    path.push("user-tokens.json");
+    Some(path)
+}
+
+fn token_cache(opt: &Options) -> Result<TokenCache> {
+    if opt.no_token_cache {
+        return Ok(TokenCache::in_memory());
+    }
+    match opt.token_cache.clone().or_else(default_token_cache_path) {
+        Some(path) => Ok(TokenCache::on_disk(path)?),
+        None => Ok(TokenCache::in_memory()),
+    }
+}
+
+fn token_verifier(opt: &Options, config: &Config) -> Result<Option<TokenVerifier>> {
+    match opt.token_jwks.as_ref().or(config.token_jwks.as_ref()) {
+        Some(path) => {
+            let jwks = std::fs::read(path)
+                .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+            Ok(Some(TokenVerifier::new().with_jwks_json(&jwks)?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn storage_session(opt: &Options, config: &Config) -> Result<Session> {
+    let mut session = StorageSession::for_profile(
+        transport(opt, config)?,
+        &profile(opt, config)?,
+        &opt.device_token,
+    )
+    .with_token_cache(Arc::new(token_cache(opt)?))
+    .with_retry_policy(RetryPolicy::new(opt.retries + 1, Backoff::default()))
+    .with_lenient_ids(lenient_ids(opt, config));
+    if let Some(verifier) = token_verifier(opt, config)? {
+        session = session.with_token_verifier(verifier);
+    }
+    Ok(session)
 }
 
-async fn register_device(opt: &Options) -> Result<()> {
-    let base_uri = Uri::from_str(&opt.auth_server)?;
+async fn register_device(opt: &Options, config: &Config) -> Result<()> {
+    let profile = profile(opt, config)?;
 
     let (code, desc, id) = match opt.cmd {
         Command::Register {
@@ -48,28 +212,22 @@ async fn register_device(opt: &Options) -> Result<()> {
         random_uuid()
     };
 
-    let req = api::DeviceTokenRequest::new(&code, &desc, &id);
+    let req = api::DeviceTokenRequest::new(code, desc, &id);
 
-    let client = https_capable_client();
-    let token = llapi::auth_get_device_bearer(&client, &base_uri, &req).await?;
+    let client = transport(opt, config)?;
+    let token = llapi::auth_get_device_bearer(&client, profile.auth_base(), &req).await?;
 
     println!("New device bearer: {}", token);
 
     Ok(())
 }
 
-async fn acquire_user_token(opt: &Options) -> Result<String> {
-    let base_uri = Uri::from_str(&opt.auth_server)?;
-    let client = https_capable_client();
-    Ok(llapi::auth_get_user_bearer(&client, &base_uri, &opt.device_token).await?)
-}
-
-fn print_docs(docs: &[api::DocsResponse], container: &str, prefix: usize) {
+fn print_docs(docs: &[api::DocsResponse], container: &api::ParentRef, prefix: usize) {
     for doc in docs.iter().filter(|d| d.parent() == container) {
         for _ in 0..prefix {
             print!("| ");
         }
-        let container = doc.node_type() == api::NodeType::CollectionType;
+        let container = *doc.node_type() == api::NodeType::CollectionType;
         if container {
             print!("+-📁");
         } else {
@@ -77,69 +235,98 @@ fn print_docs(docs: &[api::DocsResponse], container: &str, prefix: usize) {
         }
         println!(" {} ({})", doc.name(), doc.id());
         if container {
-            print_docs(docs, doc.id(), prefix + 1);
+            print_docs(docs, &doc.id().clone().into(), prefix + 1);
         }
     }
 }
 
-async fn list_server(opt: &Options) -> Result<()> {
-    let user_token = acquire_user_token(opt).await?;
-    let storage_base_uri = discover_storage_base(opt, &user_token).await?;
-    let client = https_capable_client();
-    let docs = llapi::storage_fetch_all_docs(&client, &storage_base_uri, &user_token).await?;
-    print_docs(&docs, "", 0);
+async fn list_server(opt: &Options, config: &Config) -> Result<()> {
+    let session = storage_session(opt, config)?;
+    let docs = session.list().await?;
+    print_docs(&docs, &api::ParentRef::Root, 0);
     Ok(())
 }
 
-async fn show_tokens(opt: &Options) -> Result<()> {
-    println!("Device token:\n{}", render_token(&opt.device_token)?);
-    let user_token = acquire_user_token(opt).await?;
-    println!("User token:\n{}", render_token(&user_token)?);
+async fn show_tokens(opt: &Options, config: &Config) -> Result<()> {
+    let raw = match &opt.cmd {
+        Command::ShowTokens { raw } => *raw,
+        _ => unreachable!(),
+    };
+    let device = if raw {
+        render_token(&opt.device_token)?
+    } else {
+        summarise_device_token(&opt.device_token)?
+    };
+    println!("Device token:\n{}", device);
+    let session = storage_session(opt, config)?;
+    let token = session.user_token().await?;
+    let user = if raw {
+        render_token(&token)?
+    } else {
+        summarise_user_token(&token)?
+    };
+    println!("User token:\n{}", user);
     Ok(())
 }
 
-async fn fetch_blob(opt: &Options) -> Result<()> {
-    let user_token = acquire_user_token(opt).await?;
-    let storage_base_uri = discover_storage_base(opt, &user_token).await?;
-    let client = https_capable_client();
+async fn fetch_blob(opt: &Options, config: &Config) -> Result<()> {
+    let session = storage_session(opt, config)?;
     let (id, out) = match &opt.cmd {
         Command::FetchBlob { id, out } => (id, out),
         _ => unreachable!(),
     };
-    use std::fs::File;
-    use std::io::BufWriter;
-    let mut outbuf = BufWriter::new(File::create(out)?);
     println!(
         "Written {} bytes",
-        llapi::storage_fetch_blob(&client, &storage_base_uri, &user_token, id, &mut outbuf).await?
+        session
+            .fetch_blob_file(&node_id(id, lenient_ids(opt, config))?, out, &mut |_| {})
+            .await?
     );
     Ok(())
 }
 
+async fn change_node(opt: &Options, config: &Config) -> Result<()> {
+    let session = storage_session(opt, config)?;
+    let lenient = lenient_ids(opt, config);
+    let version = match &opt.cmd {
+        Command::Move { id, parent } => {
+            let parent = parent_ref(parent, lenient)?;
+            session.move_node(&node_id(id, lenient)?, &parent).await?
+        }
+        Command::Rename { id, name } => session.rename_node(&node_id(id, lenient)?, name).await?,
+        Command::Pin { id } => session.set_bookmarked(&node_id(id, lenient)?, true).await?,
+        Command::Unpin { id } => {
+            session
+                .set_bookmarked(&node_id(id, lenient)?, false)
+                .await?
+        }
+        _ => unreachable!(),
+    };
+    println!("Updated to version {}", version);
+    Ok(())
+}
+
 mod serversync;
 
-async fn server_pull(opt: &Options) -> Result<()> {
-    let basepath = match &opt.cmd {
-        Command::ServerPull { basepath } => basepath,
+async fn server_pull(opt: &Options, config: &Config) -> Result<()> {
+    let (basepath, jobs) = match &opt.cmd {
+        Command::ServerPull { basepath, jobs } => (basepath, *jobs),
         _ => unreachable!(),
     };
 
-    let mut local_state = serversync::LocalState::new(basepath)?;
+    let mut local_state = serversync::LocalState::new(basepath, lenient_ids(opt, config))?;
 
     println!(
         "Loaded {} docs from local directory",
         local_state.count_docs()
     );
-    let user_token = acquire_user_token(opt).await?;
-    let storage_base_uri = discover_storage_base(opt, &user_token).await?;
-    let client = https_capable_client();
-    let docs = llapi::storage_fetch_all_docs(&client, &storage_base_uri, &user_token).await?;
-    let docs: HashMap<String, api::DocsResponse> =
-        docs.into_iter().map(|d| (d.id().to_owned(), d)).collect();
+    let session = storage_session(opt, config)?;
+    let docs = session.list().await?;
+    let docs: HashMap<api::NodeId, api::DocsResponse> =
+        docs.into_iter().map(|d| (d.id().clone(), d)).collect();
 
     // Now we want to synchronise docs and local-state
     // To do that, we first delete any docs which are not in the list
-    let server_uuids: HashSet<String> = docs.iter().map(|(id, _)| id.to_owned()).collect();
+    let server_uuids: HashSet<api::NodeId> = docs.keys().cloned().collect();
     local_state.remove_not_listed(&server_uuids)?;
     // Next we want to know any docs which have been changed, which basically
     // means if they're not known to the local state or have a different version
@@ -153,59 +340,95 @@ async fn server_pull(opt: &Options) -> Result<()> {
             "blobs"
         }
     );
-    for uuid in changed_uuids.iter() {
-        print!("=> {}", uuid);
-        use std::fs::File;
-        use std::io::BufWriter;
-        let temppath = local_state.download_path(uuid);
-        let mut outbuf = BufWriter::new(File::create(&temppath)?);
-        println!(
-            " - fetched {} bytes",
-            llapi::storage_fetch_blob(&client, &storage_base_uri, &user_token, uuid, &mut outbuf)
-                .await?
-        );
-        local_state.adopt_doc(&docs[uuid], &temppath)?;
+    let transfers: Vec<Transfer> = changed_uuids
+        .iter()
+        .map(|uuid| Transfer::new(uuid.clone()))
+        .collect();
+    let results = TransferScheduler::new(jobs)
+        .run(&transfers, |transfer| {
+            let (session, local_state, docs) = (&session, &local_state, &docs);
+            async move {
+                let uuid = transfer.id();
+                let doc = &docs[uuid];
+                let temppath = local_state.download_path(uuid.as_str(), doc.version());
+                let (fetched, total) = session
+                    .resume_blob_file(doc.id(), &temppath, &mut |_| {})
+                    .await?;
+                println!("=> {} - fetched {} bytes", uuid, fetched);
+                Ok((temppath, total))
+            }
+        })
+        .await;
+
+    let mut failed = 0;
+    for (uuid, res) in results {
+        match res {
+            Ok((temppath, total)) => local_state.adopt_doc(&docs[&uuid], &temppath, total)?,
+            Err(e) => {
+                println!("Unable to fetch {}: {}", uuid, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to fetch {} blobs", failed).into());
     }
     Ok(())
 }
 
-async fn server_push(opt: &Options) -> Result<()> {
-    let basepath = match &opt.cmd {
-        Command::ServerPush { basepath } => basepath,
+fn update_request(doc: &api::DocsResponse, version: usize) -> api::UpdateStatusRequest {
+    api::UpdateStatusRequest::new(
+        doc.id().clone(),
+        doc.parent().clone(),
+        doc.node_type().clone(),
+        version,
+        doc.bookmarked(),
+        doc.current_page(),
+        doc.name(),
+        doc.modified_client(),
+    )
+    .with_unknown_fields(doc.unknown_fields().clone())
+}
+
+async fn server_push(opt: &Options, config: &Config) -> Result<()> {
+    let (basepath, jobs) = match &opt.cmd {
+        Command::ServerPush { basepath, jobs } => (basepath, *jobs),
         _ => unreachable!(),
     };
 
-    let local_state = serversync::LocalState::new(basepath)?;
+    let local_state = serversync::LocalState::new(basepath, lenient_ids(opt, config))?;
 
     println!(
         "Loaded {} docs from local directory",
         local_state.count_docs()
     );
-    let user_token = acquire_user_token(opt).await?;
-    let storage_base_uri = discover_storage_base(opt, &user_token).await?;
-    let client = https_capable_client();
-    let docs = llapi::storage_fetch_all_docs(&client, &storage_base_uri, &user_token).await?;
-    let docs: HashMap<String, api::DocsResponse> =
-        docs.into_iter().map(|d| (d.id().to_owned(), d)).collect();
+    let session = storage_session(opt, config)?;
+    let docs = session.list().await?;
+    let docs: HashMap<api::NodeId, api::DocsResponse> =
+        docs.into_iter().map(|d| (d.id().clone(), d)).collect();
 
     // Now we want to synchronise docs and local-state
     // To do that, we first delete any docs which are not in the list
-    let server_uuids: HashSet<String> = docs.iter().map(|(id, _)| id.to_owned()).collect();
+    let server_uuids: HashSet<api::NodeId> = docs.keys().cloned().collect();
     let to_remove = local_state.get_not_listed(&server_uuids);
     println!(
         "We need to remove {} documents from the server first",
         to_remove.len()
     );
 
-    for uuid in to_remove.iter() {
-        llapi::storage_delete_doc(
-            &client,
-            &storage_base_uri,
-            &user_token,
-            uuid,
-            docs[uuid].version(),
-        )
-        .await?;
+    let deletions: Vec<api::DeleteRequest> = to_remove
+        .iter()
+        .map(|uuid| api::DeleteRequest::new(uuid.clone(), docs[uuid].version()))
+        .collect();
+    let mut failed = 0;
+    for (req, res) in deletions.iter().zip(session.delete_many(&deletions).await?) {
+        if let Err(e) = res {
+            println!("Unable to remove {}: {}", req.id(), e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("Failed to remove {} documents", failed).into());
     }
 
     // Next we want to know any docs which have been changed, which basically
@@ -220,61 +443,59 @@ async fn server_push(opt: &Options) -> Result<()> {
             "blobs"
         }
     );
+    // A node can only be uploaded into a collection the server knows about,
+    // so if its parent is also being sent then that has to go first
+    let mut transfers = Vec::new();
     for uuid in changed_uuids.iter() {
-        print!("=> {}", uuid);
-        use std::fs::File;
-        use std::io::{BufReader, Read};
-        let temppath = local_state.zip_path(uuid);
-        let mut reader = BufReader::new(File::open(temppath)?);
-        let mut zipfile = Vec::new();
-        reader.read_to_end(&mut zipfile)?;
-        let doc = local_state.get_doc(uuid).ok_or("WTF?")?;
-        let version = if docs.contains_key(uuid) {
-            docs[uuid].version() + 1
-        } else {
-            1
-        };
-        print!(
-            " sent {} bytes",
-            llapi::storage_upload_doc(
-                &client,
-                &storage_base_uri,
-                &user_token,
-                uuid,
-                version,
-                doc.parent(),
-                doc.node_type(),
-                doc.bookmarked(),
-                doc.current_page(),
-                doc.name(),
-                doc.modified_client(),
-                zipfile,
-            )
-            .await?
-        );
-        if version != doc.version() {
-            // We need to walk the document up to the current version
-            // in order for the server to be in sync
-            for v in version + 1..=doc.version() {
-                llapi::storage_update_doc(
-                    &client,
-                    &storage_base_uri,
-                    &user_token,
-                    &uuid,
-                    v,
-                    doc.parent(),
-                    doc.node_type(),
-                    doc.bookmarked(),
-                    doc.current_page(),
-                    doc.name(),
-                    doc.modified_client(),
-                )
-                .await?;
-            }
-            println!(", updated version to {}", doc.version());
-        } else {
-            println!(", done.");
+        let doc = local_state.get_doc(uuid.as_str()).ok_or("WTF?")?;
+        let mut transfer = Transfer::new(uuid.clone());
+        if let Some(parent) = doc.parent().node_id() {
+            transfer = transfer.after(parent.clone());
         }
+        transfers.push(transfer);
+    }
+    let results = TransferScheduler::new(jobs)
+        .run(&transfers, |transfer| {
+            let (session, local_state, docs) = (&session, &local_state, &docs);
+            async move {
+                let uuid = transfer.id();
+                let temppath = local_state.zip_path(uuid.as_str());
+                let doc = local_state
+                    .get_doc(uuid.as_str())
+                    .expect("Document vanished");
+                let version = if docs.contains_key(uuid) {
+                    docs[uuid].version() + 1
+                } else {
+                    1
+                };
+                let sent = session
+                    .upload_file(&update_request(doc, version), temppath, &mut |_| {})
+                    .await?;
+                if version != doc.version() {
+                    // We need to walk the document up to the current version
+                    // in order for the server to be in sync
+                    for v in version + 1..=doc.version() {
+                        session.update(&update_request(doc, v)).await?;
+                    }
+                    println!(
+                        "=> {} sent {} bytes, updated version to {}",
+                        uuid,
+                        sent,
+                        doc.version()
+                    );
+                } else {
+                    println!("=> {} sent {} bytes, done.", uuid, sent);
+                }
+                Ok(())
+            }
+        })
+        .await;
+
+    for (uuid, e) in results.failures() {
+        println!("Unable to send {}: {}", uuid, e);
+    }
+    if results.failed() > 0 {
+        return Err(format!("Failed to send {} blobs", results.failed()).into());
     }
     Ok(())
 }
@@ -282,12 +503,17 @@ async fn server_push(opt: &Options) -> Result<()> {
 #[tokio::main]
 async fn main() -> Result<()> {
     let opt = Options::get();
+    let config = Config::load(&opt)?;
     match &opt.cmd {
-        Command::Register { .. } => register_device(&opt).await,
-        Command::ListServer => list_server(&opt).await,
-        Command::ShowTokens => show_tokens(&opt).await,
-        Command::FetchBlob { .. } => fetch_blob(&opt).await,
-        Command::ServerPull { .. } => server_pull(&opt).await,
-        Command::ServerPush { .. } => server_push(&opt).await,
+        Command::Register { .. } => register_device(&opt, &config).await,
+        Command::ListServer => list_server(&opt, &config).await,
+        Command::ShowTokens { .. } => show_tokens(&opt, &config).await,
+        Command::FetchBlob { .. } => fetch_blob(&opt, &config).await,
+        Command::Move { .. }
+        | Command::Rename { .. }
+        | Command::Pin { .. }
+        | Command::Unpin { .. } => change_node(&opt, &config).await,
+        Command::ServerPull { .. } => server_pull(&opt, &config).await,
+        Command::ServerPush { .. } => server_push(&opt, &config).await,
     }
 }
@@ -2,23 +2,28 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
-use serde_json::{from_reader, to_writer_pretty};
+use serde_json::to_writer_pretty;
 
 use super::Result;
-use remsync_api_types::DocsResponse;
+use remsync_api_types::{from_received_reader, DocsResponse, NodeId};
 
 #[derive(Debug)]
 pub struct LocalState {
     base_path: PathBuf,
-    docs: HashMap<String, DocsResponse>,
+    lenient_ids: bool,
+    docs: HashMap<NodeId, DocsResponse>,
 }
 
 impl LocalState {
-    pub fn new<P: AsRef<Path>>(base_path: P) -> Result<LocalState> {
+    /// Load the state in `base_path`, accepting IDs which are not UUIDs
+    /// if `lenient_ids` is set
+    pub fn new<P: AsRef<Path>>(base_path: P, lenient_ids: bool) -> Result<LocalState> {
         let mut ret = Self {
             base_path: base_path.as_ref().to_owned(),
+            lenient_ids,
             docs: HashMap::new(),
         };
 
@@ -31,29 +36,29 @@ impl LocalState {
         self.docs.len()
     }
 
-    pub fn remove_not_listed(&mut self, server_uuids: &HashSet<String>) -> Result<()> {
-        let client_uuids: HashSet<String> = self.docs.keys().map(|k| k.to_owned()).collect();
+    pub fn remove_not_listed(&mut self, server_uuids: &HashSet<NodeId>) -> Result<()> {
+        let client_uuids: HashSet<NodeId> = self.docs.keys().cloned().collect();
         let to_delete = client_uuids.difference(server_uuids);
         for k in to_delete {
             println!("Removing local doc {}", k);
             self.docs.remove(k);
-            fs::remove_file(self.doc_path(k))?;
-            fs::remove_file(self.zip_path(k))?;
+            fs::remove_file(self.doc_path(k.as_str()))?;
+            fs::remove_file(self.zip_path(k.as_str()))?;
         }
         Ok(())
     }
 
-    pub fn get_not_listed(&self, server_uuids: &HashSet<String>) -> HashSet<String> {
+    pub fn get_not_listed(&self, server_uuids: &HashSet<NodeId>) -> HashSet<NodeId> {
         let mut ret = HashSet::new();
         for server_uuid in server_uuids.iter() {
             if !self.docs.contains_key(server_uuid) {
-                ret.insert(server_uuid.to_owned());
+                ret.insert(server_uuid.clone());
             }
         }
         ret
     }
 
-    pub fn find_changed(&self, docs: &HashMap<String, DocsResponse>) -> Result<HashSet<String>> {
+    pub fn find_changed(&self, docs: &HashMap<NodeId, DocsResponse>) -> Result<HashSet<NodeId>> {
         let mut ret = HashSet::new();
 
         // At this point we know that we contain some subset of the docs response
@@ -63,11 +68,11 @@ impl LocalState {
             match self.docs.get(doc.id()) {
                 Some(localdoc) => {
                     if localdoc.version() != doc.version() {
-                        ret.insert(doc.id().to_owned());
+                        ret.insert(doc.id().clone());
                     }
                 }
                 None => {
-                    ret.insert(doc.id().to_owned());
+                    ret.insert(doc.id().clone());
                 }
             }
         }
@@ -77,8 +82,8 @@ impl LocalState {
 
     pub fn find_locally_changed(
         &self,
-        docs: &HashMap<String, DocsResponse>,
-    ) -> Result<HashSet<String>> {
+        docs: &HashMap<NodeId, DocsResponse>,
+    ) -> Result<HashSet<NodeId>> {
         let mut ret = HashSet::new();
 
         // We contain a superset of the non-deleted values in docs
@@ -88,11 +93,11 @@ impl LocalState {
             match docs.get(doc.id()) {
                 Some(serverdoc) => {
                     if serverdoc.version() != doc.version() {
-                        ret.insert(doc.id().to_owned());
+                        ret.insert(doc.id().clone());
                     }
                 }
                 None => {
-                    ret.insert(doc.id().to_owned());
+                    ret.insert(doc.id().clone());
                 }
             }
         }
@@ -100,17 +105,44 @@ impl LocalState {
         Ok(ret)
     }
 
-    pub fn download_path(&self, uuid: &str) -> PathBuf {
+    /// Where to download a particular version of a document's zip
+    ///
+    /// Each version gets its own file so that an interrupted download is
+    /// only ever resumed with the same content.
+    pub fn download_path(&self, uuid: &str, version: usize) -> PathBuf {
         let mut ret = self.zip_path(uuid);
-        ret.set_extension(".zip.tmp");
+        ret.set_extension(format!("v{}.zip.tmp", version));
         ret
     }
 
-    pub fn adopt_doc(&mut self, doc: &DocsResponse, zip: &Path) -> Result<()> {
-        let outf = fs::File::create(self.doc_path(doc.id()))?;
+    /// Move a downloaded zip into place
+    ///
+    /// The download must look like a zip file, and must be `total` bytes
+    /// long if the server said how long it is.  Downloads of any other
+    /// versions of the document are cleared away.
+    pub fn adopt_doc(&mut self, doc: &DocsResponse, zip: &Path, total: Option<u64>) -> Result<()> {
+        let actual = fs::metadata(zip)?.len();
+        match total {
+            Some(total) if actual != total => {
+                return Err(format!(
+                    "Download of {} is {} bytes, the server sent {}",
+                    doc.id(),
+                    actual,
+                    total
+                )
+                .into());
+            }
+            _ => {}
+        }
+        if !is_zip(zip)? {
+            return Err(format!("Download of {} is not a zip file", doc.id()).into());
+        }
+        let uuid = doc.id().as_str();
+        let outf = fs::File::create(self.doc_path(uuid))?;
         to_writer_pretty(outf, doc)?;
-        fs::rename(zip, self.zip_path(doc.id()))?;
-        self.docs.insert(doc.id().to_owned(), doc.clone());
+        fs::rename(zip, self.zip_path(uuid))?;
+        self.remove_downloads(uuid)?;
+        self.docs.insert(doc.id().clone(), doc.clone());
         Ok(())
     }
 
@@ -134,6 +166,22 @@ impl LocalState {
         ret
     }
 
+    /// Remove any partial downloads of a document
+    fn remove_downloads(&self, uuid: &str) -> Result<()> {
+        let prefix = format!("{}.v", uuid);
+        for entry in fs::read_dir(&self.base_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let stale = name
+                .to_str()
+                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".zip.tmp"));
+            if stale {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
     fn load_data(&mut self) -> Result<()> {
         for entry in fs::read_dir(&self.base_path)? {
             let entry = entry?.path();
@@ -153,8 +201,28 @@ impl LocalState {
             .to_str()
             .ok_or("Odd, UUID not safe")?;
         let file = fs::File::open(entry)?;
-        let doc: DocsResponse = from_reader(file)?;
-        self.docs.insert(uuid.to_owned(), doc);
+        let doc: DocsResponse = from_received_reader(file, self.lenient_ids)?;
+        self.docs
+            .insert(NodeId::parse_received(uuid, self.lenient_ids)?, doc);
         Ok(())
     }
 }
+
+/// Whether the file at `path` ends the way a zip file does
+///
+/// A zip file finishes with an end of central directory record, which is
+/// 22 bytes long plus a comment of up to 64KiB.
+fn is_zip(path: &Path) -> Result<bool> {
+    const END_RECORD: &[u8] = b"PK\x05\x06";
+    const END_LENGTH: u64 = 22;
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len < END_LENGTH {
+        return Ok(false);
+    }
+    file.seek(SeekFrom::Start(len.saturating_sub(END_LENGTH + 0xffff)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let tail = &tail[..tail.len() - END_LENGTH as usize + END_RECORD.len()];
+    Ok(tail.windows(END_RECORD.len()).any(|w| w == END_RECORD))
+}